@@ -1,7 +1,13 @@
 require 'corvus/version'
+require 'corvus/settings'
 require 'corvus/compiler'
 require 'corvus/function_builder'
 require 'corvus/type_registry'
+require 'corvus/exposed'
+require 'corvus/call'
+require 'corvus/reference'
+require 'corvus/money'
+require 'corvus/output_policy'
 
 require 'thermite/fiddle'
 
@@ -10,3 +16,11 @@ File.dirname(File.dirname(__FILE__)).tap do |toplevel_dir|
                                cargo_project_path: toplevel_dir,
                                ruby_project_path: toplevel_dir)
 end
+
+# These reopen classes that are created by the native extension
+require 'corvus/args'
+require 'corvus/namespace'
+require 'corvus/script'
+require 'corvus/type'
+require 'corvus/type_inference'
+require 'corvus/type_sampler'
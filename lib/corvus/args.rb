@@ -0,0 +1,28 @@
+module Corvus
+  # The arguments passed to a namespace function callback.
+  class Args
+    include Enumerable
+
+    def each(&block)
+      return enum_for(:each) unless block
+      to_h.each(&block)
+      self
+    end
+
+    def fetch(name, *default, &block)
+      to_h.fetch(name.to_sym, *default, &block)
+    end
+
+    # other methods defined in Rust:
+    #
+    # def [](name) => value, nil for an omitted optional argument
+    # def typed(name) => value converted according to the argument's Type
+    # def to_h => { name: value }, variadic arguments as Arrays
+    # def to_typed_h => like to_h, with values converted like #typed
+    # def key?(name) => whether a value (or default) is present for name, false
+    #                   for a variadic argument given no values
+    # def function_name => String
+    # def signature => Corvus::Signature
+    #
+  end
+end
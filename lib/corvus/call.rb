@@ -0,0 +1,40 @@
+module Corvus
+  # A call to a function defined with Namespace#define, as seen by the hooks
+  # registered with Namespace#around.
+  class Call
+    attr_reader :args
+
+    def initialize(args, callback, keywords, hooks, context_keyword = false)
+      @args = args
+      @callback = callback
+      @keywords = keywords
+      @hooks = hooks
+      @context_keyword = context_keyword
+      @next_hook = 0
+    end
+
+    def function_name
+      @args.function_name
+    end
+
+    # Run the remaining hooks and then the function itself, returning its
+    # result. A hook that doesn't call this short-circuits the function.
+    def proceed
+      if @next_hook < @hooks.length
+        hook = @hooks[@next_hook]
+        @next_hook += 1
+        begin
+          hook.call(self)
+        ensure
+          @next_hook -= 1
+        end
+      elsif @context_keyword
+        @callback.call(**@args.to_h, context: @args.context)
+      elsif @keywords
+        @callback.call(**@args.to_h)
+      else
+        @callback.call(@args)
+      end
+    end
+  end
+end
@@ -1,11 +1,31 @@
 
+require 'digest'
+
 module Corvus
   class Compiler
-    attr_reader :types
+    attr_reader :types, :output_policy
 
-    def initialize
-      @ns = Namespace.new
-      @types = TypeRegistry.new
+    def initialize(ns = Namespace.new, types = TypeRegistry.new)
+      @ns = ns
+      @types = types
+      @output_policy = OutputPolicy::DEFAULT
+    end
+
+    # How results of scripts compiled from now on are returned, an OutputPolicy
+    # or the options for one:
+    #
+    #   compiler.output_policy = { keys: :string, integers: true, freeze: true }
+    def output_policy=(policy)
+      @output_policy = policy.is_a?(OutputPolicy) ? policy : OutputPolicy.new(**policy)
+    end
+
+    # A compiler whose namespace follows this one's: functions, hooks and
+    # options added here later apply to the fork unless it overrides them, but
+    # changes to the fork don't affect this compiler. Types are copied.
+    def fork
+      Compiler.new(@ns.fork, @types.dup).tap do |fork|
+        fork.output_policy = output_policy
+      end
     end
 
     def define
@@ -14,13 +34,85 @@ module Corvus
       @ns.define(*builder.into_parts)
     end
 
+    def redefine(force: false)
+      builder = FunctionBuilder.new(@types)
+      yield builder
+      @ns.redefine(*builder.into_parts, force: force)
+    end
+
+    def undefine(name)
+      @ns.undefine(name)
+    end
+
+    # Make `original` callable as `alias_name` too, e.g. after renaming a
+    # function, so stored scripts using the old names keep working. The
+    # alias's signature follows redefinitions of `original`:
+    #
+    #   compiler.alias 'numbersFrom', 'rangeFrom', 'through' => 'upTo'
+    def alias(alias_name, original, argument_names = {})
+      @ns.alias(alias_name, original, argument_names)
+    end
+
+    # See Namespace#expose, types may be anything TypeRegistry#resolve accepts
+    def expose(object, method_name, types, returns:, total: false)
+      resolved = types.map { |name, type| [name, @types.resolve(type)] }.to_h
+      @ns.expose(object, method_name, resolved,
+                 returns: @types.resolve(returns), total: total)
+    end
+
+    def expose_all(object)
+      @ns.expose_all(object, @types)
+    end
+
+    # See Namespace#signature and Namespace#metadata
+    def signature(name)
+      @ns.signature(name)
+    end
+
+    def metadata(name)
+      @ns.metadata(name)
+    end
+
+    # A Reference of the functions scripts compiled here can call
+    def reference
+      Reference.new(@ns)
+    end
+
+    def json_schema
+      @ns.json_schema
+    end
+
+    def around(hook = nil, &block)
+      @ns.around(hook, &block)
+    end
+
+    def validate_results=(enabled)
+      @ns.validate_results = enabled
+    end
+
+    # A key for caching the script compiled from `src` by this compiler
+    def cache_key(src)
+      Digest::SHA256.hexdigest("#{@ns.cache_key}\0#{output_policy.to_h}\0#{src}")
+    end
+
     def corvus_call(*args)
       @ns.corvus_call(*args)
     end
 
+    # Compile a script. `allow:` restricts it to the listed functions and
+    # capability groups, `deny:` forbids some of them; prelude functions are in
+    # the group named after their module (e.g. :math, or :other for those not
+    # in a named one). The restriction is also enforced while the script runs,
+    # whichever way it's called.
+    def compile(src, allow: nil, deny: nil)
+      compile_script(src, allow, deny).tap do |script|
+        script.output_policy = output_policy
+      end
+    end
+
     # other methods defined in Rust:
     #
-    # def compile(corvus_source_code) => CorvusScript
+    # def compile_script(corvus_source_code, allow, deny) => CorvusScript
     #
   end
 end
@@ -0,0 +1,29 @@
+module Corvus
+  # Declare which methods of a class can be exposed to Corvus scripts:
+  #
+  #   class Geo
+  #     extend Corvus::Exposed
+  #
+  #     corvus_function :distance, { distance: 'LatLon', to: 'LatLon' },
+  #                     returns: :number, total: true
+  #     def distance(distance:, to:)
+  #       # ...
+  #     end
+  #   end
+  #
+  #   compiler.expose_all(Geo.new)
+  module Exposed
+    def corvus_function(method_name, types, returns:, total: false)
+      corvus_functions[method_name] = { types: types, returns: returns, total: total }
+    end
+
+    def corvus_functions
+      @corvus_functions ||=
+        if respond_to?(:superclass) && superclass.respond_to?(:corvus_functions)
+          superclass.corvus_functions.dup
+        else
+          {}
+        end
+    end
+  end
+end
@@ -4,22 +4,59 @@ module Corvus
     def initialize(types)
       @types = types
       @total = false
+      @capabilities = []
+      @metadata = {}
     end
 
-    def arg(name, type, optional: false, variadic: false)
+    # Declare an argument. Passing a `default:` makes the argument optional;
+    # the default is checked against `type` when the function is defined.
+    # `doc:` describes the argument in the function's metadata.
+    def arg(name, type, optional: false, variadic: false, default: nil, doc: nil)
       @args ||= []
       @args << { name: name,
                  type: @types.resolve(type),
-                 optional: optional,
-                 variadic: variadic }
+                 optional: optional || !default.nil?,
+                 variadic: variadic,
+                 default: default,
+                 doc: doc }
+    end
+
+    def description(text)
+      @metadata[:description] = text
+    end
+
+    # A Corvus snippet showing how to call the function
+    def example(source)
+      (@metadata[:examples] ||= []) << source
+    end
+
+    def category(name)
+      @metadata[:category] = name.to_s
+    end
+
+    # Scripts that call a deprecated function still compile, with a warning in
+    # Script#warnings naming the `replacement` if there is one.
+    def deprecated(message = nil, replacement: nil)
+      @metadata[:deprecated] = { message: message, replacement: replacement && replacement.to_s }
     end
 
     def returns(type)
       @return_type = @types.resolve(type)
     end
 
-    def callback(&block)
-      @callback = block
+    # The callback receives a Corvus::Args, or, if it takes keyword parameters,
+    # each argument as a keyword: `fn.callback ->(countFrom:, to:) { ... }`.
+    # Keyword callbacks get the script's context (Corvus::Args#context) by
+    # declaring `context:`, unless the function has an argument of that name.
+    def callback(callable = nil, &block)
+      @callback = callable || block
+    end
+
+    # Put the function in capability groups. Scripts compiled with an `allow:`
+    # list must be granted one of them (or the function by name) to call it,
+    # and denying any of them forbids it; see Compiler#compile
+    def capabilities(*groups)
+      @capabilities.concat(groups)
     end
 
     def partial!
@@ -32,7 +69,7 @@ module Corvus
 
     def into_parts
       raise 'Function must have at least one argument' if @args.empty?
-      [@args, @return_type, @total, @callback]
+      [@args, @return_type, @total, @callback, @metadata.merge(capabilities: @capabilities)]
     end
   end
 end
\ No newline at end of file
@@ -0,0 +1,134 @@
+require 'bigdecimal'
+require 'bigdecimal/util'
+
+module Corvus
+  # Raised when amounts in different currencies are added, subtracted or compared
+  class CurrencyMismatch < StandardError; end
+
+  # An exact amount of a currency. Money literals in scripts and values of
+  # Type::Money are Money, and `calc:` on them is decimal arithmetic.
+  class Money
+    include Comparable
+
+    AMOUNT_FIRST = /\A(-?\d+(?:\.\d+)?)\s*([A-Za-z]{3})\z/
+    CURRENCY_FIRST = /\A([A-Za-z]{3})\s*(-?\d+(?:\.\d+)?)\z/
+
+    attr_reader :amount, :currency
+
+    # Parse "12.50 USD", or build from a currency and an amount
+    def self.parse(currency_or_string, amount = nil)
+      return new(amount, currency_or_string) unless amount.nil?
+      source = currency_or_string.to_s.strip
+      if (match = AMOUNT_FIRST.match(source))
+        new(match[1], match[2])
+      elsif (match = CURRENCY_FIRST.match(source))
+        new(match[2], match[1])
+      else
+        raise ArgumentError, "#{currency_or_string.inspect} is not an amount of money"
+      end
+    end
+
+    # `number` as a BigDecimal, going through its decimal representation
+    def self.decimal(number)
+      case number
+      when BigDecimal then number
+      when Integer then BigDecimal(number)
+      when Float then BigDecimal(number.to_s)
+      when Rational then number.to_d(32)
+      else BigDecimal(number.to_s)
+      end
+    rescue ArgumentError
+      raise ArgumentError, "#{number.inspect} is not a decimal number"
+    end
+
+    def initialize(amount, currency)
+      @amount = self.class.decimal(amount)
+      @currency = currency.to_s.upcase
+      freeze
+    end
+
+    def +(other)
+      Money.new(amount + same_currency(other, 'add').amount, currency)
+    end
+
+    def -(other)
+      Money.new(amount - same_currency(other, 'subtract').amount, currency)
+    end
+
+    def -@
+      Money.new(-amount, currency)
+    end
+
+    def *(other)
+      raise TypeError, "cannot multiply #{self} by #{other}" unless other.is_a?(Numeric)
+      Money.new(amount * Money.decimal(other), currency)
+    end
+
+    # Dividing by a number gives Money, dividing by Money gives their ratio
+    def /(other)
+      if other.is_a?(Money)
+        ratio = amount / same_currency(other, 'divide').amount
+        return ratio.to_f
+      end
+      raise TypeError, "cannot divide #{self} by #{other}" unless other.is_a?(Numeric)
+      Money.new(amount / Money.decimal(other), currency)
+    end
+    # compiled scripts divide with fdiv
+    alias fdiv /
+
+    def <=>(other)
+      return nil unless other.is_a?(Money)
+      amount <=> same_currency(other, 'compare').amount
+    end
+
+    def ==(other)
+      other.is_a?(Money) && currency == other.currency && amount == other.amount
+    end
+    alias eql? ==
+
+    def hash
+      [amount, currency].hash
+    end
+
+    def zero?
+      amount.zero?
+    end
+
+    # Lets `2 * money` work like `money * 2`
+    def coerce(number)
+      [Scalar.new(number), self]
+    end
+
+    def to_s
+      "#{amount.to_s('F')} #{currency}"
+    end
+
+    def inspect
+      "#<Corvus::Money #{self}>"
+    end
+
+    private
+
+    def same_currency(other, verb)
+      raise TypeError, "cannot #{verb} #{other.inspect} and #{self}" unless other.is_a?(Money)
+      return other if other.currency == currency
+      raise CurrencyMismatch, "cannot #{verb} #{other} and #{self}: currencies differ"
+    end
+
+    # A number on the left of an operator with Money on the right
+    Scalar = Struct.new(:number) do
+      def *(money)
+        money * number
+      end
+
+      def method_missing(operator, money, *)
+        raise TypeError, "cannot #{operator} #{money} and #{number}"
+      end
+
+      def respond_to_missing?(*)
+        true
+      end
+    end
+    private_constant :Scalar
+  end
+end
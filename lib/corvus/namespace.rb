@@ -0,0 +1,188 @@
+module Corvus
+  # Raised by Namespace#redefine when the new definition would break callers
+  class RedefinitionError < StandardError
+    attr_reader :problems
+
+    def initialize(name, problems)
+      @problems = problems
+      super("redefining `#{name}` would break callers:\n  #{problems.join("\n  ")}")
+    end
+  end
+
+  class Namespace
+    # `prelude:` selects the built-in functions scripts may call: `true` for all
+    # of them, `false` for none, or an Array of module names such as
+    # `[:math, :lists]`. Functions not in any of :math, :lists and :strings are
+    # in :other.
+    def self.new(prelude: true)
+      create(prelude)
+    end
+
+    # Register a hook that runs around every call to a function defined on this
+    # namespace, in both compiled and interpreted scripts. The hook receives a
+    # Corvus::Call and returns the function's result, usually by calling
+    # `call.proceed`. Hooks registered first run outermost. Built-in prelude
+    # functions (`calc:`, `each:` ...) are evaluated inside corvus_core, so
+    # hooks never see calls to them. A namespace's hooks also wrap calls made
+    # through its forks, including hooks registered after forking, and run
+    # outside the fork's own.
+    #
+    #   ns.around do |call|
+    #     started = Time.now
+    #     call.proceed
+    #   ensure
+    #     log(call.function_name, Time.now - started)
+    #   end
+    def around(hook = nil, &block)
+      raise ArgumentError, 'around takes a hook or a block, not both' if hook && block
+      hook ||= block
+      raise ArgumentError, 'around needs a hook or a block' unless hook
+      @hooks << hook
+      self
+    end
+
+    # Define a function that calls `object.method_name`. The method's keyword
+    # parameters become the function's arguments (the first one names it):
+    # required keywords are required arguments, keywords with a Ruby default are
+    # optional, and a `*splat` becomes a variadic argument. `types` maps each
+    # parameter name to its Corvus::Type.
+    def expose(object, method_name, types, returns:, total: false)
+      method = object.method(method_name)
+      args = []
+      splat = nil
+
+      method.parameters.each do |kind, name|
+        case kind
+        when :keyreq, :key
+          args << { name: name.to_s,
+                    type: exposed_type(types, method_name, name),
+                    optional: kind == :key }
+        when :rest
+          raise ArgumentError, "cannot expose #{method_name}: anonymous splat" unless name
+          splat = name
+        when :keyrest, :block
+          next
+        else
+          raise ArgumentError,
+                "cannot expose #{method_name}: positional parameter `#{name}`"
+        end
+      end
+
+      if args.empty?
+        raise ArgumentError, "cannot expose #{method_name}: it takes no keyword parameters"
+      end
+
+      callback = method.to_proc
+      if splat
+        args << { name: splat.to_s,
+                  type: exposed_type(types, method_name, splat),
+                  variadic: true }
+        callback = splat_adapter(method, splat)
+      end
+
+      define(args, returns, total, callback, source_location: method.source_location)
+    end
+
+    # Define a function whose arguments are described by `args`, an Array of
+    # `{ name:, type:, optional:, variadic:, default:, doc: }` Hashes, the first
+    # of which names the function. `options` may contain `capabilities:`, the
+    # capability groups a script must be granted (see Compiler#compile) to call
+    # the function, and documentation returned by #metadata: `description:`,
+    # `examples:` (an Array of Corvus snippets), `category:` and `deprecated:`
+    # (`true`, a message, or `{ message:, replacement: }`). `source_location:`
+    # (`[file, line]`) is where errors say the function was defined, by default
+    # the callback's.
+    def define(args, return_type, total, callback, options = {})
+      define_function(args, return_type, total, callback, options)
+      retain(args, callback)
+    end
+
+    # Replace the definition of an existing function. Unless `force` is set,
+    # this raises a RedefinitionError listing the problems if the new signature
+    # is incompatible with the old one, or if live compiled scripts would no
+    # longer type check against it.
+    def redefine(args, return_type, total, callback, options, force: false)
+      unless force
+        problems = redefinition_problems(args, return_type, total)
+        raise RedefinitionError.new(args.first[:name], problems) unless problems.empty?
+      end
+      redefine_function(args, return_type, total, callback, options)
+      retain(args, callback)
+    end
+
+    def undefine(name)
+      undefine_function(name)
+      @retained.delete(name.to_s) if @retained
+    end
+
+    # A namespace that follows this one's definitions, hooks and options,
+    # including later changes, unless it overrides them.
+    def fork
+      fork_namespace.tap { |fork| fork.instance_variable_set(:@parent, self) }
+    end
+
+    # Expose every method `object`'s class declared with
+    # Corvus::Exposed#corvus_function, resolving the declared types with
+    # `registry` (see TypeRegistry#resolve).
+    def expose_all(object, registry = TypeRegistry.new)
+      object.class.corvus_functions.each do |method_name, spec|
+        types = spec[:types].map { |name, type| [name, registry.resolve(type)] }.to_h
+        expose(object, method_name, types,
+               returns: registry.resolve(spec[:returns]), total: spec[:total])
+      end
+    end
+
+    # A JSON Schema document with a definition for each callable function,
+    # describing the arguments it's called with (see Signature#to_json_schema).
+    def json_schema
+      { '$schema' => 'http://json-schema.org/draft-07/schema#',
+        'title' => 'Corvus functions',
+        'definitions' => signatures.map do |signature|
+          [signature.arguments.first[:name], signature.to_json_schema]
+        end.to_h }
+    end
+
+    private
+
+    # Callbacks and argument defaults are only referenced from Rust, where Ruby's
+    # GC can't see them, so they are kept here while they are defined. Forks
+    # keep their parent (and so its hooks and definitions) in `@parent`.
+    def retain(args, callback)
+      (@retained ||= {})[args.first[:name].to_s] = [args, callback]
+    end
+
+    def exposed_type(types, method_name, name)
+      types.fetch(name) do
+        types.fetch(name.to_s) do
+          raise ArgumentError, "cannot expose #{method_name}: no type given for `#{name}`"
+        end
+      end
+    end
+
+    # Variadic arguments arrive as an Array keyword, the method wants them splatted
+    def splat_adapter(method, splat)
+      lambda do |**kwargs|
+        rest = kwargs.delete(splat) || []
+        method.call(*rest, **kwargs)
+      end
+    end
+
+    # other methods defined in Rust:
+    #
+    # def fork_namespace => Namespace, see #fork
+    # def redefinition_problems(args, return_type, total) => [String]
+    # def undefine_function(name)
+    # def alias(alias_name, original_name, argument_names)
+    # def corvus_call(*args)
+    # def prelude_modules => [Symbol]
+    # def functions => [String], the names scripts may call, except those of
+    #                  the :other prelude module
+    # def signature(name) => Signature with #metadata, nil unless defined from Ruby
+    # def metadata(name) => { description:, arguments: { name => doc }, examples:,
+    #                         category:, deprecated: { message:, replacement: } }
+    # def signatures => [Signature], every callable function, prelude first
+    # def cache_key => String, changes whenever a function is defined, removed,
+    #                  or redefined with a different signature
+    #
+  end
+end
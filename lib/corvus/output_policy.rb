@@ -0,0 +1,124 @@
+require 'date'
+
+module Corvus
+  # How script results are handed back to Ruby, applied the same way by
+  # Script#call and Script#call_interpreted so that both modes give identical
+  # output. See Compiler#output_policy=.
+  class OutputPolicy
+    KEY_STYLES = %i[symbol string indifferent].freeze
+
+    attr_reader :keys, :time_class
+
+    # `keys:` is the style of record keys: :symbol, :string or :indifferent
+    # (String keys that can also be read with Symbols). `integers:` turns
+    # integral numbers into Integers instead of Floats, otherwise Integers
+    # become Floats too. `time_class:` is what times become: Time, DateTime,
+    # Date, or anything with an `at` method like `Time.zone`. `freeze:`
+    # deep-freezes results.
+    def initialize(keys: :symbol, integers: false, time_class: Time, freeze: false)
+      raise ArgumentError, "keys: must be one of #{KEY_STYLES.join(', ')}" unless KEY_STYLES.include?(keys)
+      @keys = keys
+      @integers = integers
+      @time_class = time_class
+      @freeze = freeze
+      self.freeze
+    end
+
+    DEFAULT = new
+
+    def integers?
+      @integers
+    end
+
+    def freeze?
+      @freeze
+    end
+
+    def apply(value)
+      converted = convert(value)
+      freeze? ? deep_freeze(converted) : converted
+    end
+
+    def ==(other)
+      other.is_a?(OutputPolicy) && to_h == other.to_h
+    end
+
+    def to_h
+      { keys: keys, integers: integers?, time_class: time_class, freeze: freeze? }
+    end
+
+    # String keys that can also be read with Symbols
+    class IndifferentHash < Hash
+      def [](key)
+        super(key.is_a?(Symbol) ? key.to_s : key)
+      end
+
+      def fetch(key, *args, &block)
+        super(key.is_a?(Symbol) ? key.to_s : key, *args, &block)
+      end
+
+      def key?(key)
+        super(key.is_a?(Symbol) ? key.to_s : key)
+      end
+      alias has_key? key?
+      alias include? key?
+
+      def dig(key, *rest)
+        super(key.is_a?(Symbol) ? key.to_s : key, *rest)
+      end
+    end
+
+    private
+
+    def convert(value)
+      case value
+      when Hash then convert_record(value)
+      when Array then value.map { |item| convert(item) }
+      when Float then integers? && integral?(value) ? value.to_i : value
+      # from globals and callbacks, which the interpreter turns into Floats
+      when Integer then integers? && integral?(value) ? value : value.to_f
+      when Time, Date then convert_time(value)
+      else value
+      end
+    end
+
+    # Only those that Floats represent exactly
+    def integral?(number)
+      return number.abs <= 2**53 if number.is_a?(Integer)
+      number.finite? && number == number.truncate && number.abs <= 2**53
+    end
+
+    def convert_record(hash)
+      record = keys == :indifferent ? IndifferentHash.new : {}
+      hash.each do |key, item|
+        key = keys == :symbol ? key.to_sym : key.to_s if key.is_a?(Symbol) || key.is_a?(String)
+        record[key] = convert(item)
+      end
+      record
+    end
+
+    def convert_time(time)
+      if time_class == DateTime
+        time.to_datetime
+      elsif time_class == Date
+        time.to_date
+      else
+        time = time.to_time
+        time_class == Time ? time : time_class.at(time)
+      end
+    end
+
+    # Hashes and Arrays were built by #convert, other values may be the
+    # caller's and are copied before freezing
+    def deep_freeze(value)
+      case value
+      when Hash
+        value.each { |key, item| value[key] = deep_freeze(item) }
+        value.freeze
+      when Array then value.map! { |item| deep_freeze(item) }.freeze
+      when String, Time then value.frozen? ? value : value.dup.freeze
+      else value
+      end
+    end
+  end
+end
@@ -0,0 +1,133 @@
+require 'cgi'
+require 'json'
+
+module Corvus
+  # A reference of every function scripts can call in a namespace, prelude
+  # included, rendered as Markdown, HTML or JSON for help pages:
+  #
+  #   File.write('functions.md', compiler.reference.to_markdown)
+  class Reference
+    Argument = Struct.new(:name, :type, :optional, :variadic, :doc)
+
+    class Entry
+      attr_reader :name, :arguments, :return_type, :metadata
+
+      def initialize(signature)
+        @metadata = signature.metadata || {}
+        docs = @metadata[:arguments] || {}
+        @arguments = signature.arguments.map do |arg|
+          Argument.new(arg[:name], arg[:type], arg[:optional], arg[:variadic],
+                       docs[arg[:name]])
+        end
+        @name = @arguments.first.name
+        @return_type = signature.return_type
+        @total = signature.total?
+      end
+
+      def total?
+        @total
+      end
+
+      # How the function is called from Corvus, e.g.
+      # `rangeFrom: Number [through: Number] -> List<Number>`
+      def call_syntax
+        args = arguments.map do |arg|
+          part = "#{arg.name}: #{arg.type}"
+          part += '...' if arg.variadic
+          arg.optional ? "[#{part}]" : part
+        end
+        "#{args.join(' ')} -> #{return_type}"
+      end
+
+      def to_h
+        { name: name,
+          syntax: call_syntax,
+          arguments: arguments.map do |arg|
+            { name: arg.name, type: arg.type.to_s, optional: arg.optional,
+              variadic: arg.variadic, doc: arg.doc }
+          end,
+          returns: return_type.to_s,
+          total: total?,
+          description: metadata[:description],
+          examples: metadata[:examples] || [],
+          category: metadata[:category],
+          deprecated: metadata[:deprecated] }
+      end
+    end
+
+    attr_reader :entries
+
+    def initialize(namespace)
+      @entries = namespace.signatures.map { |signature| Entry.new(signature) }
+    end
+
+    def to_json(*_args)
+      JSON.pretty_generate(functions: entries.map(&:to_h))
+    end
+
+    def to_markdown
+      sections = entries.map do |entry|
+        lines = ["## #{entry.name}", '', "    #{entry.call_syntax}", '']
+        lines.concat([deprecation_note(entry), '']) if entry.metadata[:deprecated]
+        lines.concat([entry.metadata[:description], '']) if entry.metadata[:description]
+        entry.arguments.each do |arg|
+          lines << "- `#{arg.name}` (`#{arg.type}`#{qualifiers(arg)})#{": #{arg.doc}" if arg.doc}"
+        end
+        lines << '' unless entry.arguments.empty?
+        lines << "Returns `#{entry.return_type}`#{entry.total? ? '' : ', or fails'}."
+        lines << "Category: #{entry.metadata[:category]}." if entry.metadata[:category]
+        (entry.metadata[:examples] || []).each do |example|
+          lines.concat(['', '    ' + example])
+        end
+        lines.join("\n")
+      end
+      "# Functions\n\n" + sections.join("\n\n") + "\n"
+    end
+
+    def to_html
+      sections = entries.map do |entry|
+        html = ["<section id=\"#{h(entry.name)}\">",
+                "<h2>#{h(entry.name)}</h2>",
+                "<pre><code>#{h(entry.call_syntax)}</code></pre>"]
+        html << "<p class=\"deprecated\">#{h(deprecation_note(entry))}</p>" if entry.metadata[:deprecated]
+        html << "<p>#{h(entry.metadata[:description])}</p>" if entry.metadata[:description]
+        unless entry.arguments.empty?
+          html << '<ul>'
+          entry.arguments.each do |arg|
+            doc = arg.doc ? ": #{h(arg.doc)}" : ''
+            html << "<li><code>#{h(arg.name)}</code> " \
+                    "(<code>#{h(arg.type)}</code>#{h(qualifiers(arg))})#{doc}</li>"
+          end
+          html << '</ul>'
+        end
+        html << "<p>Returns <code>#{h(entry.return_type)}</code>" \
+                "#{entry.total? ? '' : ', or fails'}.</p>"
+        html << "<p>Category: #{h(entry.metadata[:category])}.</p>" if entry.metadata[:category]
+        (entry.metadata[:examples] || []).each do |example|
+          html << "<pre><code>#{h(example)}</code></pre>"
+        end
+        html << '</section>'
+        html.join("\n")
+      end
+      "<h1>Functions</h1>\n" + sections.join("\n") + "\n"
+    end
+
+    private
+
+    def qualifiers(arg)
+      [(', optional' if arg.optional), (', variadic' if arg.variadic)].compact.join
+    end
+
+    def deprecation_note(entry)
+      deprecated = entry.metadata[:deprecated]
+      note = 'Deprecated'
+      note += ", use #{deprecated[:replacement]} instead" if deprecated[:replacement]
+      note += ": #{deprecated[:message]}" if deprecated[:message]
+      note + '.'
+    end
+
+    def h(text)
+      CGI.escapeHTML(text.to_s)
+    end
+  end
+end
@@ -0,0 +1,46 @@
+module Corvus
+  class Script
+    attr_writer :output_policy
+
+    # Run the compiled Ruby code with `globals`, a Hash of names (Symbols or
+    # Strings) to values. `context` is passed to callbacks as
+    # Corvus::Args#context.
+    def call(globals = {}, context = nil)
+      output_policy.apply(corvus_compiled_call(context, **symbol_keys(globals)))
+    end
+
+    # Evaluate the script without compiling it to Ruby, taking the same
+    # arguments as #call. `calc:` uses Ruby's operators like compiled code
+    # does, so money stays exact.
+    def call_interpreted(globals = {}, context = nil)
+      corvus_enter(context)
+      begin
+        output_policy.apply(interpret(symbol_keys(globals)))
+      ensure
+        corvus_leave
+      end
+    end
+
+    # The compiler's OutputPolicy when the script was compiled
+    def output_policy
+      @output_policy || OutputPolicy::DEFAULT
+    end
+
+    private
+
+    def symbol_keys(globals)
+      globals.map { |name, value| [name.to_sym, value] }.to_h
+    end
+
+    public
+
+    # other methods defined in Rust, or by the compiled ruby_code:
+    #
+    # def corvus_compiled_call(context = nil, **globals) => result
+    # def input_types => { name => Corvus::Type }
+    # def return_type => Corvus::Type
+    # def ruby_code => String
+    # def warnings => [String], e.g. for calls to deprecated functions
+    #
+  end
+end
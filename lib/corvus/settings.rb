@@ -0,0 +1,13 @@
+module Corvus
+  class << self
+    attr_writer :validate_results_by_default
+
+    # Whether new namespaces check callback results against the declared return
+    # type (see Namespace#validate_results=). Defaults to on in development and
+    # test environments, where the cost is worth catching buggy callbacks early.
+    def validate_results_by_default?
+      return @validate_results_by_default unless @validate_results_by_default.nil?
+      %w[development test].include?(ENV['CORVUS_ENV'] || ENV['RAILS_ENV'] || ENV['RACK_ENV'])
+    end
+  end
+end
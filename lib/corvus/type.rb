@@ -0,0 +1,246 @@
+require 'date'
+require 'time'
+
+module Corvus
+  class Type
+    # The outcome of #unify or #assignment_to: the resulting type and the types
+    # bound to type variables, or the mismatch that prevented it.
+    Unification = Struct.new(:type, :substitutions, :mismatch) do
+      def ok?
+        mismatch.nil?
+      end
+    end
+
+    # `path` locates the mismatch, e.g. `people[].location` or `f.inputs[0]`
+    Mismatch = Struct.new(:path, :expected, :actual, :message) do
+      def to_s
+        path.empty? ? message : "#{path}: #{message}"
+      end
+    end
+
+    # A record type from `{ name => { type:, optional: } }`. Records are open,
+    # values may have fields besides these, unless `exact` is set.
+    def self.record(fields, exact: false)
+      build_record(fields, exact)
+    end
+
+    # Parse a type expression such as
+    # `[name: String, tags?: List<String>, f: (Number, Number) -> Bool]`, the
+    # syntax #to_s prints. Other names are looked up in `named_types`, a Hash
+    # of names to Types, and unknown lowercase names are type variables.
+    # Raises ArgumentError pointing at the problem if `src` doesn't parse.
+    def self.parse(src, named_types = {})
+      parse_expression(src, named_types)
+    end
+
+    # The most specific type `value` and every one of `samples` have, e.g. to
+    # describe a new data source from example payloads. Records are open, a
+    # field is optional when some samples lack it or have it nil, and samples
+    # that conflict give Any.
+    def self.infer(value, samples: [])
+      TypeInference.infer([value, *samples])
+    end
+
+    # Build a type from a JSON Schema Hash or JSON String. Supports the subset
+    # #to_json_schema produces: primitives, `date-time` strings as Time, arrays,
+    # objects (optional unless `required`) and exported blocks.
+    def self.from_json_schema(schema)
+      schema = JSON.parse(schema) if schema.is_a?(String)
+      import_json_schema(schema)
+    end
+
+    # Where and how a value checked with #check_value doesn't fit: `path` like
+    # `people[3].location.lat` (empty for the value itself), the `expected`
+    # Type, the `actual` Ruby class name and an `excerpt` of the value's
+    # inspect output.
+    ValueError = Struct.new(:path, :expected, :actual, :excerpt, :message) do
+      def to_s
+        path.empty? ? message : "#{path}: #{message}"
+      end
+    end
+
+    # The ValueErrors for `value`, empty if it is of this type. With
+    # `all: false`, checking stops at the first error.
+    def check_value(value, all: true)
+      check_value_errors(value, all).map do |error|
+        ValueError.new(*error.values_at(:path, :expected, :actual, :excerpt, :message))
+      end
+    end
+
+    # The outcome of #coerce: the converted value and the ValueErrors that
+    # remain, as #check_value reports them.
+    Coercion = Struct.new(:value, :errors) do
+      def ok?
+        errors.empty?
+      end
+    end
+
+    # Convert input such as form fields or CSV rows towards this type: numeric
+    # Strings become numbers, "true" and "false" booleans, ISO-8601 Strings,
+    # epoch seconds, Dates and other time-like objects become Times, "12.50
+    # USD" Strings become Corvus::Money, and record keys are matched to field
+    # names whether they are Strings or Symbols, ignoring case, spaces, dashes
+    # and underscores. Values that can't be converted are left as they were and
+    # reported in the Coercion's errors.
+    def coerce(value)
+      coerced = coerce_value(value)
+      Coercion.new(coerced, check_value(coerced))
+    end
+
+    # A random value of this type that #check_value accepts. The same `seed`
+    # gives the same value, `size` bounds the length of lists and strings and
+    # the magnitude of numbers.
+    def sample(seed: Random.new_seed, size: 10)
+      TypeSampler.new(seed, size).sample(self)
+    end
+
+    # Values of this type that are simpler than `value`, simplest first
+    def shrink(value)
+      TypeSampler.shrink(self, value)
+    end
+
+    # Check a property against `runs` samples of growing size, e.g. to fuzz a
+    # callback. Returns nil if the block accepted every sample, otherwise the
+    # first sample it rejected (by returning false or raising), shrunk as far
+    # as it keeps being rejected.
+    #
+    #   type.counterexample { |args| compiler.corvus_call(:scale, args) }
+    def counterexample(runs: 100, seed: Random.new_seed, size: 10, &property)
+      passes = ->(value) { (property.call(value) rescue false) ? true : false }
+      random = Random.new(seed)
+      runs.times do |run|
+        value = sample(seed: random.rand(2**32), size: 1 + run * size / runs)
+        next if passes.call(value)
+        loop do
+          smaller = shrink(value).find { |candidate| !passes.call(candidate) }
+          break unless smaller
+          value = smaller
+        end
+        return value
+      end
+      nil
+    end
+
+    def assignable_to?(other)
+      assignment_to(other).ok?
+    end
+
+    # Check that values of this type can be used where `other` is expected, e.g.
+    # that a script's return type fits the slot it's plugged into. The
+    # Unification's type is `other` with its type variables substituted.
+    def assignment_to(other)
+      unification(assign_to(other))
+    end
+
+    # The most specific type values of both this type and `other` have
+    def unify(other)
+      unification(unify_with(other))
+    end
+
+    # Walk the type depth first, yielding each type and its path from here: field
+    # names for records, `:element` for list elements, `[:input, i]` and
+    # `:output` for blocks.
+    def each_type(path = [], &block)
+      return enum_for(:each_type, path) unless block
+      yield self, path
+      case kind
+      when :list then element_type.each_type(path + [:element], &block)
+      when :record
+        fields.sort.each { |name, type| type.each_type(path + [name], &block) }
+      when :block
+        inputs.each_with_index do |input, i|
+          input.each_type(path + [[:input, i]], &block)
+        end
+        output.each_type(path + [:output], &block)
+      end
+    end
+
+    protected
+
+    def coerce_value(value)
+      case kind
+      when :number then coerce_number(value)
+      when :bool then coerce_bool(value)
+      when :time then coerce_time(value)
+      when :money then value.is_a?(String) ? (Money.parse(value) rescue value) : value
+      when :string then value.is_a?(Symbol) ? value.to_s : value
+      when :list
+        value.is_a?(Array) ? value.map { |item| element_type.coerce_value(item) } : value
+      when :record then value.is_a?(Hash) ? coerce_record(value) : value
+      else value
+      end
+    end
+
+    private
+
+    def coerce_number(value)
+      case value
+      when Float then value
+      when Numeric then value.to_f
+      when String then Float(value.strip) rescue value
+      else value
+      end
+    end
+
+    def coerce_bool(value)
+      case value.is_a?(String) && value.strip.downcase
+      when 'true' then true
+      when 'false' then false
+      else value
+      end
+    end
+
+    # See "Times" in the README for how values become Times
+    def coerce_time(value)
+      case value
+      when Time then value
+      when Numeric then Time.at(value)
+      when String then Time.iso8601(value.strip) rescue value
+      else value.respond_to?(:to_time) ? value.to_time : value
+      end
+    end
+
+    def coerce_record(hash)
+      field_types = fields
+      by_key = field_types.keys.map { |name| [field_key(name), name] }.to_h
+      hash.each_with_object({}) do |(key, item), coerced|
+        name = field_types.key?(key.to_s) ? key.to_s : by_key[field_key(key)]
+        if name
+          coerced[name.to_sym] = field_types[name].coerce_value(item)
+        else
+          coerced[key] = item
+        end
+      end
+    end
+
+    def field_key(name)
+      name.to_s.downcase.delete(' _-')
+    end
+
+    def unification(result)
+      details = result[:mismatch]
+      mismatch = details &&
+                 Mismatch.new(*details.values_at(:path, :expected, :actual, :message))
+      Unification.new(result[:type], result[:substitutions], mismatch)
+    end
+
+    # other methods defined in Rust:
+    #
+    # def kind => :any, :number, :string, :bool, :time, :money, :list, :record,
+    #             :block or :var
+    # def element_type => Type of a list's elements, or nil
+    # def inputs => [Type] a block takes, or nil
+    # def output => Type a block returns, or nil
+    # def var_name => String naming a type variable, or nil
+    # def fields => { name => Type } for records, {} otherwise
+    # def optional_fields, required_fields => [String], sorted
+    # def to_h => { kind:, ... } describing the type recursively with plain Ruby
+    #             values, see #each_type
+    # def open? => true for records that allow undeclared fields
+    # def check_value_errors(value, all) => [Hash] for #check_value
+    # def to_s => String that .parse reads back
+    # def to_json_schema => Hash with String keys
+    # def assign_to(other), unify_with(other) => Hash for #unification
+    #
+  end
+end
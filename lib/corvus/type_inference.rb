@@ -0,0 +1,71 @@
+require 'date'
+
+module Corvus
+  # Infers the most specific Type that a set of sample values share, see
+  # Type.infer. Works on shapes, plain Ruby descriptions of types, so that
+  # samples can be merged before any Type is built: a primitive kind Symbol,
+  # `[:list, element_shape]`, `{ name => [shape, optional] }` for records, or
+  # nil when nothing is known yet (e.g. the elements of an empty Array).
+  module TypeInference
+    PRIMITIVES = { any: Type::Any, number: Type::Number, string: Type::String,
+                   bool: Type::Bool, time: Type::Time, money: Type::Money }.freeze
+
+    module_function
+
+    def infer(values)
+      to_type(values.map { |value| shape_of(value) }.reduce { |a, b| merge(a, b) })
+    end
+
+    # Only what Type#check_value accepts as each kind is given that kind, so
+    # that values always satisfy the type inferred from them: other Numerics
+    # (Rationals, BigDecimals), Symbols, nil list items and Hashes with
+    # non-String keys are :any.
+    def shape_of(value)
+      case value
+      when nil then nil
+      when Money then :money
+      when Integer, Float then :number
+      when String then :string
+      when true, false then :bool
+      when Time, Date then :time
+      when Array
+        items = value.map { |item| item.nil? ? :any : shape_of(item) }
+        [:list, items.reduce { |a, b| merge(a, b) }]
+      when Hash
+        return :any unless value.keys.all? { |key| key.is_a?(String) || key.is_a?(Symbol) }
+        value.map { |key, item| [key.to_s, [shape_of(item), item.nil?]] }.to_h
+      else value.respond_to?(:to_time) ? :time : :any
+      end
+    end
+
+    # The shape values of both `a` and `b` fit: record fields missing from
+    # either become optional, and conflicting shapes become :any.
+    def merge(a, b)
+      return b if a.nil?
+      return a if b.nil?
+      if a.is_a?(Hash) && b.is_a?(Hash)
+        (a.keys | b.keys).map do |name|
+          shape_a, optional_a = a.fetch(name, [nil, true])
+          shape_b, optional_b = b.fetch(name, [nil, true])
+          [name, [merge(shape_a, shape_b), optional_a || optional_b]]
+        end.to_h
+      elsif a.is_a?(Array) && b.is_a?(Array)
+        [:list, merge(a[1], b[1])]
+      else
+        a == b ? a : :any
+      end
+    end
+
+    def to_type(shape)
+      case shape
+      when nil then Type::Any
+      when Symbol then PRIMITIVES.fetch(shape)
+      when Array then Type.list(to_type(shape[1]))
+      when Hash
+        Type.record(shape.map do |name, (field_shape, optional)|
+          [name, { type: to_type(field_shape), optional: optional }]
+        end.to_h)
+      end
+    end
+  end
+end
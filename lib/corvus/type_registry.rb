@@ -4,6 +4,11 @@ module Corvus
       @named_types = {}
     end
 
+    def initialize_copy(other)
+      super
+      @named_types = @named_types.dup
+    end
+
     def define(name, type)
       raise "#{name} already defined" if @named_types[name]
       @named_types[name] = resolve(type)
@@ -12,13 +17,24 @@ module Corvus
     def resolve(type)
       if type.is_a?(Type) then type
       elsif type.is_a?(Symbol) then resolve_by_name(type.to_s)
-      elsif type.is_a?(String) then resolve_by_name(type)
+      elsif type.is_a?(String) then type =~ /\A\w+\z/ ? resolve_by_name(type) : parse(type)
       elsif type.is_a?(Hash) then resolve_record(type)
       else
         "Cannot resolve type from #{type.class.name} #{type}"
       end
     end
 
+    # Parse a type expression (see Type.parse) that may use the types defined
+    # here by name
+    def parse(src)
+      Type.parse(src, @named_types)
+    end
+
+    # Like resolving a Hash, but values of the record may not have other fields
+    def exact_record(fields)
+      resolve_record(fields, exact: true)
+    end
+
     def list_of(type)
       Type.list(resolve(type))
     end
@@ -37,17 +53,18 @@ module Corvus
       when 'number', 'num' then Type::Number
       when 'string' then Type::String
       when 'time' then Type::Time
+      when 'money' then Type::Money
       else
         raise "#{name} is not defined" unless @named_types[name]
         @named_types[name]
       end
     end
 
-    def resolve_record(hash)
+    def resolve_record(hash, exact: false)
       resolved_fields = hash.map do |(key, val)|
         [key.to_s, resolve_field(val)]
       end
-      Type.record(resolved_fields.to_h)
+      Type.record(resolved_fields.to_h, exact: exact)
     end
 
     def resolve_field(field)
@@ -0,0 +1,97 @@
+module Corvus
+  # Random values of a Type, for property tests of scripts and callbacks. See
+  # Type#sample, Type#shrink and Type#counterexample.
+  class TypeSampler
+    EDGE_NUMBERS = [0.0, -0.0, 1.0, -1.0, 0.1, 1e-9, -1e-9, 2.0**53, -(2.0**53),
+                    Float::MAX, -Float::MAX, Float::MIN].freeze
+    # Strings are passed to Corvus as C strings, so never contain NUL
+    CHARACTERS = [*'a'..'z', *'A'..'Z', *'0'..'9', ' ', '-', '_', "\n",
+                  'é', 'ß', 'Ω', 'я', '日', '本', '🙂', "\u200B"].freeze
+    # Times up to this many seconds after the epoch, in whole seconds: the
+    # interpreter can't calculate with earlier times or fractions of a second
+    MAX_TIME = 2**32 - 1
+    CURRENCIES = %w[USD EUR GBP JPY CHF].freeze
+
+    def initialize(seed, size)
+      @random = Random.new(seed)
+      @size = size
+    end
+
+    def sample(type)
+      case type.kind
+      when :number then sample_number
+      when :string then Array.new(@random.rand(0..@size)) { pick(CHARACTERS) }.join
+      when :bool then @random.rand(2).zero?
+      when :time then Time.at(@random.rand(0..MAX_TIME))
+      when :money then sample_money
+      when :list then Array.new(@random.rand(0..@size)) { sample(type.element_type) }
+      when :record then sample_record(type)
+      when :block
+        result = sample(type.output)
+        ->(*_inputs) { result }
+      else sample(pick([Type::Number, Type::String, Type::Bool]))
+      end
+    end
+
+    # Values of `type` simpler than `value`, simplest first
+    def self.shrink(type, value)
+      candidates =
+        case type.kind
+        when :number then [0.0, value.truncate.to_f, value / 2]
+        when :string then ['', value[0, value.length / 2], value[1..-1]]
+        when :bool then [false]
+        when :time then [Time.at(0), Time.at(value.to_i), Time.at(value.to_i / 2)]
+        when :money then [Money.new(0, value.currency), Money.new(value.amount.truncate, value.currency)]
+        when :list then shrink_list(type, value)
+        when :record then shrink_record(type, value)
+        else []
+        end
+      candidates.uniq.reject { |candidate| candidate.eql?(value) }
+    end
+
+    def self.shrink_list(type, items)
+      halves = [[], items.take(items.size / 2), items.drop(items.size / 2)]
+      removed = items.each_index.map { |i| items.take(i) + items.drop(i + 1) }
+      shrunk = items.each_with_index.flat_map do |item, i|
+        shrink(type.element_type, item).map { |smaller| items.take(i) + [smaller] + items.drop(i + 1) }
+      end
+      halves + removed + shrunk
+    end
+
+    def self.shrink_record(type, record)
+      dropped = type.optional_fields.map(&:to_sym).select { |name| record.key?(name) }.map do |name|
+        record.reject { |key, _| key == name }
+      end
+      shrunk = record.flat_map do |name, item|
+        shrink(type.fields[name.to_s], item).map { |smaller| record.merge(name => smaller) }
+      end
+      dropped + shrunk
+    end
+
+    private
+
+    def sample_number
+      return pick(EDGE_NUMBERS) if @random.rand(4).zero?
+      magnitude = 10.0**@random.rand(0..[@size, 300].min)
+      (@random.rand * 2 - 1) * magnitude
+    end
+
+    # Up to two decimal places, with the currency picked like any other value
+    def sample_money
+      cents = @random.rand(-(10**[@size, 15].min)..10**[@size, 15].min)
+      Money.new(BigDecimal(cents) / 100, pick(CURRENCIES))
+    end
+
+    def sample_record(type)
+      optional = type.optional_fields
+      type.fields.sort.each_with_object({}) do |(name, field_type), record|
+        next if optional.include?(name) && @random.rand(2).zero?
+        record[name.to_sym] = sample(field_type)
+      end
+    end
+
+    def pick(choices)
+      choices[@random.rand(choices.size)]
+    end
+  end
+end
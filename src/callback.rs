@@ -0,0 +1,233 @@
+//! Calling conventions for the Ruby callbacks behind `Namespace#define`
+
+use std::rc::Rc;
+
+use ruru::{AnyObject, Array, Boolean, Class, Fixnum, Hash, Object, Proc, RString, Symbol};
+use ruru::result::{Error, Result};
+
+use corvus_core::{Apply, Value};
+use corvus_core::signature::Signature;
+
+use error::Error as CorvusError;
+use value::CorvusValue;
+use value_check;
+use classes::corvus_args::{value_of, CorvusArgs};
+use classes::corvus_namespace::Settings;
+use classes::corvus_signature::ArgDefaults;
+
+/// The keyword keyword-convention callbacks can declare to receive `Args#context`.
+const CONTEXT_KEYWORD: &'static str = "context";
+
+/// How Corvus arguments are handed to a Ruby callback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallingConvention {
+  /// `proc { |args| args['to'] }` receives a single `Corvus::Args`.
+  Args,
+  /// `->(countFrom:, to:) { ... }` receives each Corvus argument as a keyword, and
+  /// the script's context as `context:` if it declares that keyword (and the
+  /// function has no argument called `context`).
+  Keywords { context: bool },
+}
+
+impl CallingConvention {
+  /// Choose the convention for `rproc` from its parameter list. Callbacks taking
+  /// keywords must accept exactly the arguments in `signature` (or use `**rest`).
+  pub fn detect(
+    rproc: &Proc,
+    signature: &Signature,
+    defaults: &ArgDefaults,
+  ) -> Result<CallingConvention> {
+    let params = parameters(rproc)?;
+    if params.iter().any(|&(ref kind, _)| kind.starts_with("key")) {
+      let context = check_keyword_parameters(&params, signature, defaults)?;
+      Ok(CallingConvention::Keywords { context: context })
+    } else {
+      Ok(CallingConvention::Args)
+    }
+  }
+
+  /// Call `rproc` with the arguments in `apply`, through the namespace's hooks if
+  /// there are any. Prelude functions don't come through here, so they are never
+  /// hooked.
+  pub fn call(
+    &self,
+    rproc: &Proc,
+    apply: Apply<CorvusValue>,
+    signature: &Rc<Signature>,
+    defaults: &Rc<ArgDefaults>,
+    settings: &Settings,
+  ) -> Result<AnyObject> {
+    if settings.has_hooks() {
+      let hooks = settings.all_hooks();
+      let context = settings.current_context();
+      let args = CorvusArgs::wrap(apply, signature.clone(), defaults.clone(), context);
+      let (keywords, context_keyword) = match *self {
+        CallingConvention::Args => (false, false),
+        CallingConvention::Keywords { context } => (true, context),
+      };
+      let call = Class::from_existing("Corvus").get_nested_class("Call").new_instance(Some(&[
+        args.to_any_object(),
+        rproc.to_any_object(),
+        Boolean::new(keywords).to_any_object(),
+        hooks.to_any_object(),
+        Boolean::new(context_keyword).to_any_object(),
+      ]));
+      return Ok(call.send("proceed", None));
+    }
+
+    let arg = match *self {
+      CallingConvention::Args => {
+        let context = settings.current_context();
+        CorvusArgs::wrap(apply, signature.clone(), defaults.clone(), context).to_any_object()
+      }
+      // a trailing Hash is passed to the proc as its keyword arguments
+      CallingConvention::Keywords { context } => {
+        let mut kwargs = keyword_arguments(&apply, signature, defaults)?;
+        if context {
+          kwargs.store(Symbol::new(CONTEXT_KEYWORD), settings.current_context());
+        }
+        kwargs.to_any_object()
+      }
+    };
+    Ok(rproc.call(Some(&[arg])))
+  }
+}
+
+/// The arguments in `apply` as a Hash with Symbol keys. Omitted optional arguments
+/// without a default are left out, variadic arguments are collected into Arrays.
+pub fn keyword_arguments(
+  apply: &Apply<CorvusValue>,
+  signature: &Signature,
+  defaults: &ArgDefaults,
+) -> Result<Hash> {
+  let mut hash = Hash::new();
+  for arg in signature.args() {
+    if let Some(value) = value_of(apply, defaults, arg)? {
+      hash.store(Symbol::new(&arg.name), value.to_any_object());
+    }
+  }
+  Ok(hash)
+}
+
+/// Check a callback's result against the return type in `signature`. `defined_at`
+/// is the callback's source location, for the error message.
+pub fn validate_result(
+  signature: &Signature,
+  defined_at: &str,
+  result: &CorvusValue,
+) -> ::std::result::Result<(), CorvusError> {
+  let return_type = signature.get_return_type();
+  let errors = value_check::check(return_type, result, true);
+  if errors.is_empty() {
+    return Ok(());
+  }
+  let messages: Vec<String> = errors.iter().map(|e| format!("{}", e)).collect();
+  Err(CorvusError::Corvus(format!(
+    "`{}` (defined at {}) returned {} instead of {}: {}",
+    function_name(signature),
+    defined_at,
+    ruby_class_name(result),
+    return_type,
+    messages.join(", ")
+  )))
+}
+
+/// The Corvus name of the function, i.e. its first keyword.
+pub fn function_name(signature: &Signature) -> String {
+  signature.args().next().map(|arg| arg.name.clone()).unwrap_or_default()
+}
+
+/// A `[file, line]` source location as `"file:line"`: `given` unless it's nil, e.g.
+/// that of the method `rproc` adapts, otherwise `Proc#source_location`.
+pub fn source_location(rproc: &Proc, given: AnyObject) -> String {
+  let location = if given.is_nil() { rproc.send("source_location", None) } else { given };
+  location
+    .try_convert_to::<Array>()
+    .ok()
+    .and_then(|location| {
+      let file = location.at(0).try_convert_to::<RString>().ok()?;
+      let line = location.at(1).try_convert_to::<Fixnum>().ok()?;
+      Some(format!("{}:{}", file.to_str(), line.to_i64()))
+    })
+    .unwrap_or_else(|| "(unknown location)".to_string())
+}
+
+fn ruby_class_name(value: &CorvusValue) -> String {
+  if value.callable() {
+    return "a block".to_string();
+  }
+  let class_name = value.to_any_object().class().send("name", None);
+  class_name
+    .try_convert_to::<RString>()
+    .map(|name| format!("a {}", name.to_str()))
+    .unwrap_or_else(|_| "a value".to_string())
+}
+
+/// `Proc#parameters` as `(kind, name)` pairs, e.g. `("keyreq", Some("to"))`.
+pub fn parameters(rproc: &Proc) -> Result<Vec<(String, Option<String>)>> {
+  let params: Array = rproc.send("parameters", None).try_convert_to()?;
+  let mut result = Vec::with_capacity(params.length());
+  for param in params {
+    let param: Array = param.try_convert_to()?;
+    let kind: Symbol = param.at(0).try_convert_to()?;
+    let name = param.at(1).try_convert_to::<Symbol>().ok().map(|name| name.to_string());
+    result.push((kind.to_string(), name));
+  }
+  Ok(result)
+}
+
+/// Fail unless the keyword parameters match `signature`. Returns whether the
+/// callback takes the reserved `context:` keyword.
+fn check_keyword_parameters(
+  params: &[(String, Option<String>)],
+  signature: &Signature,
+  defaults: &ArgDefaults,
+) -> Result<bool> {
+  let function_name = function_name(signature);
+  let mut accepts_any = false;
+  let mut accepts_context = false;
+  let mut accepted: Vec<&str> = vec![];
+
+  for &(ref kind, ref name) in params {
+    match (kind.as_str(), name) {
+      ("keyreq", &Some(ref name)) | ("key", &Some(ref name))
+        if name == CONTEXT_KEYWORD && signature.arg(name).is_none() =>
+      {
+        accepts_context = true
+      }
+      ("keyreq", &Some(ref name)) | ("key", &Some(ref name)) => {
+        let arg = signature.arg(name).ok_or_else(|| {
+          Error::ArgumentError(format!(
+            "callback for `{}` takes `{}:`, which is not one of its arguments",
+            function_name, name
+          ))
+        })?;
+        if kind == "keyreq" && !arg.required && !defaults.contains_key(name) {
+          return Err(Error::ArgumentError(format!(
+            "callback for `{}` requires `{}:`, but the argument is optional",
+            function_name, name
+          )));
+        }
+        accepted.push(name);
+      }
+      ("keyrest", _) => accepts_any = true,
+      ("block", _) => {}
+      _ => {
+        return Err(Error::ArgumentError(format!(
+          "callback for `{}` cannot mix keyword and positional parameters",
+          function_name
+        )))
+      }
+    }
+  }
+
+  if !accepts_any {
+    if let Some(arg) = signature.args().find(|arg| !accepted.contains(&arg.name.as_str())) {
+      return Err(Error::ArgumentError(format!(
+        "callback for `{}` does not accept `{}:`",
+        function_name, arg.name
+      )));
+    }
+  }
+  Ok(accepts_context)
+}
@@ -0,0 +1,61 @@
+//! Per-script restrictions on which functions may be called
+
+/// The functions a script is granted, by name or by capability group. Prelude
+/// functions belong to the group named after their prelude module.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capabilities {
+  /// When set, only these names and groups are allowed.
+  pub allow: Option<Vec<String>>,
+  pub deny: Vec<String>,
+}
+
+impl Capabilities {
+  /// Whether a function called `name` in capability `groups` may be called. Denials
+  /// take precedence over the allow list.
+  pub fn permits(&self, name: &str, groups: &[String]) -> bool {
+    let matches = |list: &[String]| list.iter().any(|entry| entry == name || groups.contains(entry));
+    let allowed = self.allow.as_ref().map_or(true, |allow| matches(allow));
+    allowed && !matches(&self.deny)
+  }
+
+  /// Whether every function may be called.
+  pub fn is_unrestricted(&self) -> bool {
+    self.allow.is_none() && self.deny.is_empty()
+  }
+
+  pub fn check(&self, name: &str, groups: &[String]) -> Result<(), String> {
+    if self.permits(name, groups) {
+      Ok(())
+    } else {
+      Err(format!("`{}` is not allowed in this script", name))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Capabilities;
+
+  #[test]
+  fn test_allow_by_name_or_group() {
+    let caps = Capabilities {
+      allow: Some(vec!["lookup".into(), "math".into()]),
+      deny: vec![],
+    };
+    assert!(caps.permits("lookup", &[]));
+    assert!(caps.permits("calc", &["math".into()]));
+    assert!(!caps.permits("sendEmail", &["network".into()]));
+  }
+
+  #[test]
+  fn test_deny_wins() {
+    let caps = Capabilities {
+      allow: None,
+      deny: vec!["network".into()],
+    };
+    assert!(caps.permits("calc", &["math".into()]));
+    assert!(!caps.permits("sendEmail", &["network".into()]));
+    assert!(!caps.is_unrestricted());
+    assert!(Capabilities::default().is_unrestricted());
+  }
+}
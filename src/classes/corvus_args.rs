@@ -1,14 +1,76 @@
-use ruru::{AnyObject, Array, Class, NilClass, Object, RString};
+use std::rc::Rc;
+
+use ruru::{AnyObject, Boolean, Class, Hash, NilClass, Object, RString, Symbol};
 use ruru::result::Error;
 
-use corvus_core::{Apply, INamespace, SharedNamespace};
+use corvus_core::{Apply, Type};
+use corvus_core::signature::{Argument, Signature};
 
-use helpers::raise_and_return_nil;
+use helpers::{raise_and_return_nil, stringify_key};
+use callback::keyword_arguments;
 use value::CorvusValue;
+use classes::corvus_signature::{ArgDefaults, CorvusSignature};
 
 pub struct Args {
-    ns: SharedNamespace<CorvusValue>,
     apply: Apply<CorvusValue>,
+    signature: Rc<Signature>,
+    defaults: Rc<ArgDefaults>,
+    context: AnyObject,
+}
+
+impl Args {
+    fn argument(&self, name: &str) -> Result<&Argument, Error> {
+        self.signature.arg(name).ok_or_else(|| {
+            Error::TypeError(format!("`{}` is not a valid argument name", name))
+        })
+    }
+
+    fn value_of(&self, arg: &Argument) -> Result<Option<CorvusValue>, Error> {
+        value_of(&self.apply, &self.defaults, arg)
+    }
+
+    fn typed_value_of(&self, arg: &Argument) -> Result<Option<AnyObject>, Error> {
+        let ty = if arg.variadic { Type::list_of(arg.ty.clone()) } else { arg.ty.clone() };
+        match self.value_of(arg)? {
+            Some(value) => value.to_typed_ruby(&ty).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn to_typed_hash(&self) -> Result<Hash, Error> {
+        let mut hash = Hash::new();
+        for arg in self.signature.args() {
+            if let Some(value) = self.typed_value_of(arg)? {
+                hash.store(Symbol::new(&arg.name), value);
+            }
+        }
+        Ok(hash)
+    }
+}
+
+/// The value passed for `arg`, falling back to its default. Variadic arguments are
+/// collected into an Array. Returns `None` for an omitted optional argument without a
+/// default.
+pub fn value_of(
+    apply: &Apply<CorvusValue>,
+    defaults: &ArgDefaults,
+    arg: &Argument,
+) -> Result<Option<CorvusValue>, Error> {
+    let mut values = apply.iter()
+        .filter_map(|&(ref this_name, ref val)| {
+            if *this_name == arg.name { Some(val.clone()) } else { None }
+        });
+
+    if arg.variadic {
+        return Ok(Some(values.collect()));
+    }
+    match values.next() {
+        Some(first) => Ok(Some(first)),
+        None if arg.required => {
+            Err(Error::TypeError(format!("missing required argument `{}`", arg.name)))
+        }
+        None => Ok(defaults.get(&arg.name).cloned()),
+    }
 }
 
 wrappable_struct!(Args, ArgsWrapper, WRAPPER);
@@ -19,45 +81,80 @@ methods!(
     CorvusArgs,
     itself,
 
-    fn ruby_args_get(name: RString) -> AnyObject {
-        name.and_then(|name| {
+    fn ruby_args_get(name: AnyObject) -> AnyObject {
+        name.and_then(stringify_key).and_then(|name| {
             let data = itself.get_data(&*WRAPPER);
-            let ns = data.ns.borrow();
-            let signature = ns.get_signature(data.apply.func_name()).ok_or_else(|| {
-                Error::TypeError(format!("function `{}` is not defined", data.apply.func_name()))
-            })?;
+            let arg = data.argument(&name)?;
+            data.value_of(arg).map(|value| {
+                value.map(|v| v.to_any_object()).unwrap_or_else(|| NilClass::new().to_any_object())
+            })
+        }).unwrap_or_else(raise_and_return_nil)
+    }
+
+    fn ruby_args_typed(name: AnyObject) -> AnyObject {
+        name.and_then(stringify_key).and_then(|name| {
+            let data = itself.get_data(&*WRAPPER);
+            let arg = data.argument(&name)?;
+            data.typed_value_of(arg).map(|value| {
+                value.unwrap_or_else(|| NilClass::new().to_any_object())
+            })
+        }).unwrap_or_else(raise_and_return_nil)
+    }
+
+    fn ruby_args_to_h() -> AnyObject {
+        let data = itself.get_data(&*WRAPPER);
+        keyword_arguments(&data.apply, &data.signature, &data.defaults)
+            .map(|hash| hash.to_any_object())
+            .unwrap_or_else(raise_and_return_nil)
+    }
 
-            let arg = signature.arg(name.to_str()).ok_or_else(|| {
-                Error::TypeError(format!("`{}` is not a valid argument name", name.to_str()))
-            })?;
+    fn ruby_args_to_typed_h() -> AnyObject {
+        let data = itself.get_data(&*WRAPPER);
+        data.to_typed_hash()
+            .map(|hash| hash.to_any_object())
+            .unwrap_or_else(raise_and_return_nil)
+    }
+
+    fn ruby_args_function_name() -> RString {
+        RString::new(itself.get_data(&*WRAPPER).apply.func_name())
+    }
+
+    fn ruby_args_signature() -> AnyObject {
+        let signature: &Signature = &itself.get_data(&*WRAPPER).signature;
+        CorvusSignature::new(signature.clone())
+    }
 
-            let mut values = data.apply.iter()
-                .filter_map(|&(ref this_name, ref val)| {
-                    if this_name == name.to_str() { Some(val.to_any_object()) } else { None }
-                });
+    fn ruby_args_context() -> AnyObject {
+        itself.get_data(&*WRAPPER).context.clone()
+    }
 
+    fn ruby_args_has_key(name: AnyObject) -> Boolean {
+        let present = name.and_then(stringify_key).and_then(|name| {
+            let data = itself.get_data(&*WRAPPER);
+            let arg = data.argument(&name)?;
             if arg.variadic {
-                let array: Array = values.collect();
-                Ok(array.to_any_object())
-            } else {
-                if let Some(first) = values.next() {
-                    Ok(first)
-                } else if arg.required {
-                    Err(Error::TypeError(format!("missing required argument `{}`", name.to_str())))
-                } else {
-                    Ok(NilClass::new().to_any_object())
-                }
+                // a variadic argument always has a value, possibly an empty list
+                return Ok(data.apply.iter().any(|&(ref this_name, _)| *this_name == arg.name));
             }
-        }).unwrap_or_else(raise_and_return_nil)
+            data.value_of(arg).map(|value| value.is_some())
+        });
+        Boolean::new(present.unwrap_or(false))
     }
 );
 
 
 impl CorvusArgs {
-    pub fn wrap(ns: SharedNamespace<CorvusValue>, apply: Apply<CorvusValue>) -> Self {
+    pub fn wrap(
+        apply: Apply<CorvusValue>,
+        signature: Rc<Signature>,
+        defaults: Rc<ArgDefaults>,
+        context: AnyObject,
+    ) -> Self {
         let data = Args {
-            ns: ns,
             apply: apply,
+            signature: signature,
+            defaults: defaults,
+            context: context,
         };
         get_corvus_class!("Args").wrap_data(data, &*WRAPPER)
     }
@@ -66,5 +163,12 @@ impl CorvusArgs {
 pub fn init() {
     init_corvus_class!("Args", |class| {
         class.def("[]", ruby_args_get);
+        class.def("typed", ruby_args_typed);
+        class.def("to_h", ruby_args_to_h);
+        class.def("to_typed_h", ruby_args_to_typed_h);
+        class.def("key?", ruby_args_has_key);
+        class.def("function_name", ruby_args_function_name);
+        class.def("signature", ruby_args_signature);
+        class.def("context", ruby_args_context);
     });
 }
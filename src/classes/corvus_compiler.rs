@@ -5,8 +5,11 @@ use ruru::result::Error;
 use corvus_core::{parse, type_of, ParseRule};
 
 use emitter;
+use syntax::function_names;
+use namespace_layer::{CompiledScript, NamespaceLayer};
 use helpers::raise_and_return_nil;
-use classes::corvus_namespace::CorvusNamespace;
+use capabilities::Capabilities;
+use classes::corvus_namespace::{string_list, CorvusNamespace};
 use classes::corvus_script::CorvusScript;
 
 class!(CorvusCompiler);
@@ -15,18 +18,31 @@ methods!(
   CorvusCompiler,
   itself,
 
-  fn corvus_compiler_compile(src: RString) -> AnyObject {
+  fn corvus_compiler_compile(src: RString, allow: AnyObject, deny: AnyObject) -> AnyObject {
     src.and_then(|src| {
       let corvus_ns: CorvusNamespace = itself.instance_variable_get("@ns").try_convert_to()?;
-      let ns = corvus_ns.clone_rc();
-      let (stx, ty, inferred_env) = {
+      let layer = corvus_ns.layer();
+      let allow = allow?;
+      let capabilities = Capabilities {
+        allow: if allow.is_nil() { None } else { Some(string_list(allow)?) },
+        deny: string_list(deny?)?,
+      };
+      let (stx, ty, inferred_env, warnings) = {
+        let ns = NamespaceLayer::ns(&layer).map_err(Error::TypeError)?;
         let ns = ns.try_borrow().map_err(|e| Error::TypeError(format!("{}", e)))?;
         let stx = parse(&*ns, ParseRule::script, src.to_str()).map_err(|e| Error::ArgumentError(format!("{}", e)))?;
+        let functions = function_names(&stx);
+        layer.check_allowed(&functions).map_err(Error::TypeError)?;
+        for name in &functions {
+          capabilities.check(name, &layer.capabilities_of(name)).map_err(Error::TypeError)?;
+        }
         let (ty, inferred_env) = type_of(&*ns, empty(), &stx).map_err(|err| Error::TypeError(format!("{}", err)))?;
-        (stx, ty, inferred_env)
+        let warnings = layer.deprecation_warnings(&functions);
+        (stx, ty, inferred_env, warnings)
       };
       let ruby_code = emitter::emit(&stx).map_err(|err| Error::TypeError(format!("emit: {:?}", err)))?;
-      Ok(CorvusScript::new(ns, stx, ty, inferred_env, ruby_code))
+      let compiled = CompiledScript { source: src.to_string(), stx: stx };
+      Ok(CorvusScript::new(layer, compiled, capabilities, ty, inferred_env, ruby_code, warnings))
     }).unwrap_or_else(raise_and_return_nil)
   }
 );
@@ -34,5 +50,5 @@ methods!(
 pub fn init() {
   Class::from_existing("Corvus")
     .get_nested_class("Compiler")
-    .def("compile", corvus_compiler_compile);
+    .def("compile_script", corvus_compiler_compile);
 }
@@ -1,17 +1,135 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
 use ruru;
 use ruru::result::Error as RError;
-use ruru::{AnyObject, Array, Boolean, Class, NilClass, Object, Proc, Symbol};
+use ruru::{AnyObject, Array, Boolean, Class, Hash, NilClass, Object, Proc, RString, Symbol};
 
-use corvus_core::{Apply, Namespace, SharedNamespace};
+use corvus_core::Apply;
 
-use helpers::{raise_and_return_nil, rewrite_error};
+use helpers::{raise_and_return_nil, rewrite_error, stringify_key, truthy};
+use callback::{function_name, source_location, validate_result, CallingConvention};
+use capabilities::Capabilities;
+use metadata::Metadata;
+use namespace_layer::{Callback, Definition, NamespaceLayer};
+use prelude::Prelude;
 use value::CorvusValue;
 use error::Error;
 use classes::corvus_type::CorvusType;
-use classes::corvus_signature::build_signature;
-use classes::corvus_args::CorvusArgs;
+use classes::corvus_signature::{build_signature, CorvusSignature};
+
+/// Options read by the callbacks of a namespace each time they are called. Like
+/// definitions, a fork's settings follow its parent's, including changes made
+/// after the fork: the parent's hooks wrap the fork's own, and options the fork
+/// hasn't set are the parent's.
+pub struct Settings {
+  parent: Option<Rc<Settings>>,
+  /// Check callback results against their declared return type, or None to
+  /// follow the parent.
+  validate_results: Cell<Option<bool>>,
+  /// Procs registered with `Namespace#around` on this namespace, outermost first.
+  /// This is also the namespace's `@hooks` so that Ruby keeps it alive.
+  pub hooks: Array,
+  /// The scripts currently running, innermost last.
+  frames: RefCell<Vec<Frame>>,
+}
+
+/// What a callback needs to know about the script that called it.
+struct Frame {
+  context: AnyObject,
+  capabilities: Rc<Capabilities>,
+}
+
+impl Settings {
+  fn new() -> Self {
+    let validate_results = truthy(
+      Class::from_existing("Corvus").send("validate_results_by_default?", None)
+    );
+    Settings {
+      parent: None,
+      validate_results: Cell::new(Some(validate_results)),
+      hooks: Array::new(),
+      frames: RefCell::new(vec![]),
+    }
+  }
 
-wrappable_struct!(SharedNamespace<CorvusValue>, NamespaceWrapper, WRAPPER);
+  /// Settings for a fork, which follow `parent` until they are changed.
+  fn fork(parent: &Rc<Settings>) -> Self {
+    Settings {
+      parent: Some(parent.clone()),
+      validate_results: Cell::new(None),
+      hooks: Array::new(),
+      frames: RefCell::new(vec![]),
+    }
+  }
+
+  pub fn validate_results(&self) -> bool {
+    match self.validate_results.get() {
+      Some(enabled) => enabled,
+      None => self.parent.as_ref().map_or(false, |parent| parent.validate_results()),
+    }
+  }
+
+  pub fn set_validate_results(&self, enabled: bool) {
+    self.validate_results.set(Some(enabled));
+  }
+
+  /// Whether this namespace or one of its parents has hooks, without allocating.
+  pub fn has_hooks(&self) -> bool {
+    self.hooks.length() > 0 || self.parent.as_ref().map_or(false, |parent| parent.has_hooks())
+  }
+
+  /// The hooks of the parents and then of this namespace, outermost first.
+  pub fn all_hooks(&self) -> Array {
+    let mut hooks = match self.parent {
+      Some(ref parent) => parent.all_hooks(),
+      None => Array::new(),
+    };
+    for hook in self.hooks.clone().into_iter() {
+      hooks.push(hook);
+    }
+    hooks
+  }
+
+  /// Called when a script starts running, with the context it was given.
+  pub fn enter_script(&self, context: AnyObject, capabilities: Rc<Capabilities>) {
+    self.frames.borrow_mut().push(Frame {
+      context: context,
+      capabilities: capabilities,
+    });
+  }
+
+  pub fn leave_script(&self) {
+    self.frames.borrow_mut().pop();
+  }
+
+  /// The context passed to the innermost running script, or nil.
+  pub fn current_context(&self) -> AnyObject {
+    self
+      .frames
+      .borrow()
+      .last()
+      .map(|frame| frame.context.clone())
+      .unwrap_or_else(|| NilClass::new().to_any_object())
+  }
+
+  /// Fail unless the innermost running script may call `name`. Calls made
+  /// directly from Ruby are not restricted.
+  pub fn check_capabilities(&self, name: &str, groups: &[String]) -> Result<(), String> {
+    match self.frames.borrow().last() {
+      Some(frame) => frame.capabilities.check(name, groups),
+      None => Ok(()),
+    }
+  }
+}
+
+lazy_static!(
+  static ref SYM_CAPABILITIES: Symbol = Symbol::new("capabilities");
+  static ref SYM_SOURCE_LOCATION: Symbol = Symbol::new("source_location");
+);
+
+wrappable_struct!(Rc<NamespaceLayer>, NamespaceWrapper, WRAPPER);
 
 class!(CorvusNamespace);
 verify_with_class_name!(CorvusNamespace, "Namespace");
@@ -19,39 +137,97 @@ methods!(
   CorvusNamespace,
   itself,
 
-  fn corvus_namespace_new() -> AnyObject {
-    Namespace::new_with_prelude().map_err(RError::TypeError).map(|ns: Namespace<CorvusValue>| ns.into_shared()).map(|ns| {
-      get_corvus_class!("Namespace").wrap_data(ns, &*WRAPPER)
+  fn corvus_namespace_self_create(prelude: AnyObject) -> AnyObject {
+    prelude.and_then(select_prelude).and_then(|prelude| {
+      Ok(wrap_layer(NamespaceLayer::root(Settings::new(), prelude)))
     }).unwrap_or_else(raise_and_return_nil)
   }
 
+  fn corvus_namespace_fork() -> AnyObject {
+    let layer = itself.get_data(&*WRAPPER);
+    wrap_layer(NamespaceLayer::fork(layer, Settings::fork(&layer.settings)))
+  }
+
   fn corvus_namespace_define(
     args: Array,
     return_type: CorvusType,
     total: Boolean,
-    rproc: Proc
+    rproc: Proc,
+    options: Hash
   ) -> AnyObject {
-    rproc.and_then(|rproc| {
-      let signature = build_signature(args?, return_type?, total?)?;
-      let ns = itself.get_data(&*WRAPPER);
-      let callback = Box::new({
-        let ns = ns.clone();
-        move |args: Apply<CorvusValue>| {
-          // todo rb_protect ??
-          let proc_result = rproc.call(Some(&[CorvusArgs::wrap(ns.clone(), args).to_any_object()]));
-          Ok(CorvusValue::from(proc_result))
+    Ok(()).and_then(|_| {
+      let definition = build_definition(args?, return_type?, total?, rproc?, options?)?;
+      itself.get_data(&*WRAPPER).define(definition).map_err(RError::TypeError)?;
+      Ok(NilClass::new().to_any_object())
+    }).unwrap_or_else(raise_and_return_nil)
+  }
+
+  fn corvus_namespace_redefine_function(
+    args: Array,
+    return_type: CorvusType,
+    total: Boolean,
+    rproc: Proc,
+    options: Hash
+  ) -> AnyObject {
+    Ok(()).and_then(|_| {
+      let definition = build_definition(args?, return_type?, total?, rproc?, options?)?;
+      itself.get_data(&*WRAPPER).redefine(definition).map_err(RError::TypeError)?;
+      Ok(NilClass::new().to_any_object())
+    }).unwrap_or_else(raise_and_return_nil)
+  }
+
+  fn corvus_namespace_redefinition_problems(
+    args: Array,
+    return_type: CorvusType,
+    total: Boolean
+  ) -> AnyObject {
+    Ok(()).and_then(|_| {
+      let (signature, _defaults) = build_signature(args?, return_type?, total?)?;
+      // only the signature matters here, the callback is never called
+      let callback: Callback = Rc::new(|_, _: &Settings| Err(Error::Nil));
+      let definition = Definition::new(signature, callback);
+      let problems = itself.get_data(&*WRAPPER).redefinition_problems(&definition).map_err(RError::TypeError)?;
+      let problems: Array = problems.into_iter().map(|p| RString::from(p).to_any_object()).collect();
+      Ok(problems.to_any_object())
+    }).unwrap_or_else(raise_and_return_nil)
+  }
+
+  fn corvus_namespace_undefine(name: AnyObject) -> AnyObject {
+    name.and_then(stringify_key).and_then(|name| {
+      itself.get_data(&*WRAPPER).undefine(&name).map_err(RError::TypeError)?;
+      Ok(NilClass::new().to_any_object())
+    }).unwrap_or_else(raise_and_return_nil)
+  }
+
+  fn corvus_namespace_alias(alias_name: AnyObject, original: AnyObject, argument_names: Hash) -> AnyObject {
+    Ok(()).and_then(|_| {
+      let alias_name = stringify_key(alias_name?)?;
+      let original = stringify_key(original?)?;
+      let mut renames = HashMap::new();
+      let mut conversion_error = None;
+      argument_names?.each(|key, value| {
+        match stringify_key(key).and_then(|key| stringify_key(value).map(|value| (key, value))) {
+          Ok((key, value)) => { renames.insert(key, value); }
+          Err(err) => conversion_error = Some(err),
         }
       });
-      ns.borrow_mut().insert(signature, callback).map_err(RError::TypeError)?;
+      if let Some(err) = conversion_error {
+        return Err(err);
+      }
+      NamespaceLayer::alias(itself.get_data(&*WRAPPER), &alias_name, &original, &renames)
+        .map_err(RError::TypeError)?;
       Ok(NilClass::new().to_any_object())
     }).unwrap_or_else(raise_and_return_nil)
   }
 
   fn corvus_namespace_corvus_call(args: Array) -> AnyObject {
     args.map(|args| args.into_iter().collect()).and_then(|args: Vec<AnyObject>| {
-      let ns = itself.get_data(&*WRAPPER);
+      let layer = itself.get_data(&*WRAPPER);
       let apply = build_apply(args).map_err(rewrite_error(|m| format!("build apply: {}", m)))?;
-      ns.borrow().eval_apply(apply).map(|v| v.to_any_object()).map_err(|err| match err {
+      layer.check_allowed(Some(&apply.func_name().to_string())).map_err(RError::TypeError)?;
+      let ns = NamespaceLayer::ns(layer).map_err(RError::TypeError)?;
+      let result = ns.borrow().eval_apply(apply);
+      result.map(|v| v.to_any_object()).map_err(|err| match err {
         Error::Ruru(err) => err,
         Error::Corvus(err) => RError::TypeError(format!("Corvus error: {}", err)),
         Error::Nil => RError::TypeError(format!("nil value passed to nu")),
@@ -59,19 +235,151 @@ methods!(
       }).map_err(rewrite_error(|m| format!("eval apply: {}", m)))
     }).unwrap_or_else(raise_and_return_nil)
   }
+
+  fn corvus_namespace_set_validate_results(enabled: AnyObject) -> AnyObject {
+    enabled.map(|enabled| {
+      itself.get_data(&*WRAPPER).settings.set_validate_results(truthy(enabled.clone()));
+      enabled
+    }).unwrap_or_else(raise_and_return_nil)
+  }
+
+  fn corvus_namespace_validate_results() -> Boolean {
+    Boolean::new(itself.get_data(&*WRAPPER).settings.validate_results())
+  }
+
+  fn corvus_namespace_prelude_modules() -> Array {
+    itself.get_data(&*WRAPPER).prelude.modules().into_iter()
+      .map(|module| Symbol::new(module).to_any_object())
+      .collect()
+  }
+
+  fn corvus_namespace_functions() -> Array {
+    itself.get_data(&*WRAPPER).function_names().into_iter()
+      .map(|name| RString::from(name).to_any_object())
+      .collect()
+  }
+
+  fn corvus_namespace_signature(name: AnyObject) -> AnyObject {
+    name.and_then(stringify_key).map(|name| {
+      match itself.get_data(&*WRAPPER).lookup(&name) {
+        Some(definition) => CorvusSignature::with_metadata(definition.signature.clone(), &definition.metadata),
+        None => NilClass::new().to_any_object(),
+      }
+    }).unwrap_or_else(raise_and_return_nil)
+  }
+
+  fn corvus_namespace_metadata(name: AnyObject) -> AnyObject {
+    name.and_then(stringify_key).map(|name| {
+      match itself.get_data(&*WRAPPER).lookup(&name) {
+        Some(definition) => definition.metadata.to_ruby().to_any_object(),
+        None => NilClass::new().to_any_object(),
+      }
+    }).unwrap_or_else(raise_and_return_nil)
+  }
+
+  fn corvus_namespace_signatures() -> AnyObject {
+    NamespaceLayer::signatures(itself.get_data(&*WRAPPER)).map(|signatures| {
+      signatures.into_iter()
+        .map(|(signature, metadata)| CorvusSignature::with_metadata(signature, &metadata))
+        .collect::<Array>()
+        .to_any_object()
+    }).map_err(RError::TypeError).unwrap_or_else(raise_and_return_nil)
+  }
+
+  fn corvus_namespace_cache_key() -> RString {
+    RString::from(itself.get_data(&*WRAPPER).cache_key())
+  }
 );
 
+/// `true` or `nil` for the whole prelude, `false` for none of it, or an Array of
+/// module names.
+fn select_prelude(selection: AnyObject) -> ruru::result::Result<Prelude> {
+  if selection.is_nil() || selection.value().is_true() {
+    return Ok(Prelude::All);
+  }
+  if selection.value().is_false() {
+    return Ok(Prelude::Only(vec![]));
+  }
+  Prelude::select(string_list(selection)?).map_err(RError::ArgumentError)
+}
+
+/// Build a definition from the arguments of `Namespace#define`. `options` may contain
+/// `capabilities:`, the capability groups scripts need to call the function, and the
+/// documentation read by `Metadata::from_ruby`.
+fn build_definition(
+  args: Array,
+  return_type: CorvusType,
+  total: Boolean,
+  rproc: Proc,
+  options: Hash,
+) -> ruru::result::Result<Definition> {
+  let metadata = Metadata::from_ruby(&args, &options)?;
+  let (signature, defaults) = build_signature(args, return_type, total)?;
+  let convention = CallingConvention::detect(&rproc, &signature, &defaults)?;
+  let capabilities = string_list(options.at(&*SYM_CAPABILITIES))?;
+  let callback: Callback = Rc::new({
+    let name = function_name(&signature);
+    let signature = Rc::new(signature.clone());
+    let defaults = Rc::new(defaults);
+    let capabilities = capabilities.clone();
+    let defined_at = source_location(&rproc, options.at(&*SYM_SOURCE_LOCATION));
+    move |args: Apply<CorvusValue>, settings: &Settings| {
+      settings.check_capabilities(&name, &capabilities).map_err(Error::Corvus)?;
+      // todo rb_protect ??
+      let proc_result = convention.call(&rproc, args, &signature, &defaults, settings)?;
+      let result = CorvusValue::from(proc_result);
+      if settings.validate_results() {
+        validate_result(&signature, &defined_at, &result)?;
+      }
+      Ok(result)
+    }
+  });
+  let mut definition = Definition::new(signature, callback);
+  definition.capabilities = capabilities;
+  definition.metadata = metadata;
+  Ok(definition)
+}
+
+/// An Array of Symbols or Strings as Strings, nil as an empty list.
+pub fn string_list(list: AnyObject) -> ruru::result::Result<Vec<String>> {
+  if list.is_nil() {
+    return Ok(vec![]);
+  }
+  let list: Array = list.try_convert_to()?;
+  list.into_iter().map(stringify_key).collect()
+}
+
+fn wrap_layer(layer: Rc<NamespaceLayer>) -> AnyObject {
+  let hooks = layer.settings.hooks.clone();
+  let mut namespace: AnyObject = get_corvus_class!("Namespace").wrap_data(layer, &*WRAPPER);
+  namespace.instance_variable_set("@hooks", hooks);
+  namespace
+}
+
 impl CorvusNamespace {
-  pub fn clone_rc(&self) -> SharedNamespace<CorvusValue> {
+  pub fn layer(&self) -> Rc<NamespaceLayer> {
     self.get_data(&*WRAPPER).clone()
   }
 }
 
 pub fn init() {
   init_corvus_class!("Namespace", |class| {
-    class.def_self("new", corvus_namespace_new);
-    class.def("define", corvus_namespace_define);
+    class.def_self("create", corvus_namespace_self_create);
+    class.def("define_function", corvus_namespace_define);
+    class.def("fork_namespace", corvus_namespace_fork);
+    class.def("redefine_function", corvus_namespace_redefine_function);
+    class.def("redefinition_problems", corvus_namespace_redefinition_problems);
+    class.def("undefine_function", corvus_namespace_undefine);
+    class.def("alias", corvus_namespace_alias);
     class.def("corvus_call", corvus_namespace_corvus_call);
+    class.def("validate_results=", corvus_namespace_set_validate_results);
+    class.def("validate_results?", corvus_namespace_validate_results);
+    class.def("prelude_modules", corvus_namespace_prelude_modules);
+    class.def("functions", corvus_namespace_functions);
+    class.def("signature", corvus_namespace_signature);
+    class.def("metadata", corvus_namespace_metadata);
+    class.def("signatures", corvus_namespace_signatures);
+    class.def("cache_key", corvus_namespace_cache_key);
   });
 }
 
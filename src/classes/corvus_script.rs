@@ -1,17 +1,24 @@
 //! A compiled Corvus script
 
 use std::collections::HashMap;
+use std::rc::Rc;
 use ruru;
-use ruru::{AnyObject, Class, Hash, NilClass, Object, RString, Symbol};
-use corvus_core::{Eval, InferredEnv, Scope, SharedNamespace, Syntax, Type};
+use ruru::{AnyObject, Array, Class, Hash, NilClass, Object, RString, Symbol};
+use corvus_core::{Eval, InferredEnv, Scope, Syntax, Type};
 
 use classes::corvus_type::CorvusType;
+use namespace_layer::{CompiledScript, NamespaceLayer};
+use ruby_calc;
+use capabilities::Capabilities;
 use value::CorvusValue;
 use helpers::{build_apply, raise_and_return_nil};
 
 pub struct ScriptData {
-  ns: SharedNamespace<CorvusValue>,
-  stx: Syntax,
+  layer: Rc<NamespaceLayer>,
+  script: Rc<CompiledScript>,
+  /// The script's syntax with `calc:` routed to Ruby, see `ruby_calc`.
+  interpreted: Syntax,
+  capabilities: Rc<Capabilities>,
 }
 
 wrappable_struct!(ScriptData, ScriptWrapper, WRAPPER);
@@ -27,6 +34,8 @@ methods!(
     NilClass::new()
   }
 
+  /// Called by `Script#call_interpreted` between `corvus_enter` and `corvus_leave`,
+  /// so that the script's frame is popped even if a callback raises.
   fn corvus_script_interpret(globals: Hash) -> AnyObject {
     globals.and_then(|globals| {
       let script_data = itself.get_data(&*WRAPPER);
@@ -36,9 +45,25 @@ methods!(
           scope.insert(sym.to_string(), CorvusValue::from(value));
         });
       });
-      script_data.stx.eval(&script_data.ns, &scope).map_err(|e| ruru::result::Error::TypeError(format!("{}", e))).map(|v| v.to_any_object())
+      let layer = &script_data.layer;
+      let ns = NamespaceLayer::ns(layer).map_err(ruru::result::Error::TypeError)?;
+      let result = script_data.interpreted.eval(&ns, &scope);
+      result.map_err(|e| ruru::result::Error::TypeError(format!("{}", e))).map(|v| v.to_any_object())
     }).unwrap_or_else(raise_and_return_nil)
   }
+
+  fn corvus_script_enter(context: AnyObject) -> NilClass {
+    if let Ok(context) = context {
+      let data = itself.get_data(&*WRAPPER);
+      data.layer.settings.enter_script(context, data.capabilities.clone());
+    }
+    NilClass::new()
+  }
+
+  fn corvus_script_leave() -> NilClass {
+    itself.get_data(&*WRAPPER).layer.settings.leave_script();
+    NilClass::new()
+  }
 );
 
 // Not using macros for this because we can do all kinds of unsafe shit
@@ -54,8 +79,15 @@ pub extern "C" fn corvus_script_private_corvus_call(
   build_apply(args)
     .and_then(|apply| {
       let data = itself.get_data(&*WRAPPER);
-      let result = data
-        .ns
+      let layer = &data.layer;
+      let name = apply.func_name().to_string();
+      // nothing to check for most scripts, so skip looking the function up
+      if !data.capabilities.is_unrestricted() || !layer.prelude.is_complete() {
+        layer.check_allowed(Some(&name)).map_err(RError::TypeError)?;
+        data.capabilities.check(&name, &layer.capabilities_of(&name)).map_err(RError::TypeError)?;
+      }
+      let ns = NamespaceLayer::ns(layer).map_err(RError::TypeError)?;
+      let result = ns
         .borrow()
         .eval_apply(apply)
         .map(|v| v.to_any_object())
@@ -73,14 +105,24 @@ pub extern "C" fn corvus_script_private_corvus_call(
 
 impl CorvusScript {
   pub fn new(
-    ns: SharedNamespace<CorvusValue>,
-    stx: Syntax,
+    layer: Rc<NamespaceLayer>,
+    compiled: CompiledScript,
+    capabilities: Capabilities,
     return_type: Type,
     input_types: InferredEnv,
     ruby_code: String,
+    warnings: Vec<String>,
   ) -> AnyObject {
-    let mut script: AnyObject =
-      get_corvus_class!("Script").wrap_data(ScriptData { ns: ns, stx: stx }, &*WRAPPER);
+    let interpreted = ruby_calc::route(&compiled.stx);
+    let compiled = Rc::new(compiled);
+    layer.register_script(&compiled);
+    let data = ScriptData {
+      layer: layer,
+      script: compiled,
+      interpreted: interpreted,
+      capabilities: Rc::new(capabilities),
+    };
+    let mut script: AnyObject = get_corvus_class!("Script").wrap_data(data, &*WRAPPER);
     let code = RString::from(ruby_code);
     script.send("instance_eval", Some(&[code.to_any_object()]));
     script.instance_variable_set("@ruby_code", code);
@@ -89,6 +131,8 @@ impl CorvusScript {
       "@input_types",
       type_env_to_ruby_hash(input_types).to_any_object(),
     );
+    let warnings: Array = warnings.into_iter().map(|w| RString::from(w).to_any_object()).collect();
+    script.instance_variable_set("@warnings", warnings);
     script
   }
 }
@@ -96,6 +140,7 @@ impl CorvusScript {
 attr_reader!(CorvusScript, corvus_script_input_types, input_types);
 attr_reader!(CorvusScript, corvus_script_return_type, return_type);
 attr_reader!(CorvusScript, corvus_script_ruby_code, ruby_code);
+attr_reader!(CorvusScript, corvus_script_warnings, warnings);
 
 fn type_env_to_ruby_hash(env: HashMap<String, Type>) -> Hash {
   let mut hash = Hash::new();
@@ -109,10 +154,13 @@ pub fn init() {
   init_corvus_class!("Script", |class| {
     class.def_self("new", corvus_script_disallow_new);
     class.def("corvus_call", corvus_script_private_corvus_call);
-    class.def("call_interpreted", corvus_script_interpret);
+    class.def("interpret", corvus_script_interpret);
+    class.def("corvus_enter", corvus_script_enter);
+    class.def("corvus_leave", corvus_script_leave);
 
     class.def("input_types", corvus_script_input_types);
     class.def("return_type", corvus_script_return_type);
     class.def("ruby_code", corvus_script_ruby_code);
+    class.def("warnings", corvus_script_warnings);
   });
 }
@@ -1,8 +1,16 @@
+use std::collections::HashMap;
+
 use ruru::{AnyObject, Array, Boolean, Class, Hash, Object, RString, Symbol};
+use ruru::result::Error;
 
+use corvus_core::Type;
 use corvus_core::signature::{Argument, Signature};
 
 use helpers::{raise_and_return_nil, truthy};
+use json_schema;
+use metadata::Metadata;
+use value::CorvusValue;
+use value_check;
 use classes::corvus_type::CorvusType;
 
 wrappable_struct!(Signature, SignatureWrapper, WRAPPER);
@@ -10,10 +18,14 @@ wrappable_struct!(Signature, SignatureWrapper, WRAPPER);
 lazy_static!(
   static ref SYM_NAME: Symbol = Symbol::new("name");
   static ref SYM_TYPE: Symbol = Symbol::new("type");
-  static ref SYM_OPTIONAL: Symbol = Symbol::new("required");
+  static ref SYM_OPTIONAL: Symbol = Symbol::new("optional");
   static ref SYM_VARIADIC: Symbol = Symbol::new("variadic");
+  static ref SYM_DEFAULT: Symbol = Symbol::new("default");
 );
 
+/// Default values for optional arguments, keyed by argument name.
+pub type ArgDefaults = HashMap<String, CorvusValue>;
+
 class!(CorvusSignature);
 verify_with_class_name!(CorvusSignature, "Signature");
 methods!(
@@ -22,18 +34,64 @@ methods!(
 
   fn corvus_signature_new(args: Array, return_type: CorvusType, total: Boolean) -> AnyObject {
     Ok(()).and_then(|_| {
-      let signature = build_signature(args?, return_type?, total?)?;
-      Ok(get_corvus_class!("Signature").wrap_data(signature, &*WRAPPER))
+      let (signature, _defaults) = build_signature(args?, return_type?, total?)?;
+      Ok(CorvusSignature::new(signature))
     }).unwrap_or_else(raise_and_return_nil)
   }
+
+  fn corvus_signature_arguments() -> Array {
+    let signature: &Signature = itself.get_data(&*WRAPPER);
+    signature.args().map(|arg| {
+      let mut hash = Hash::new();
+      hash.store(&*SYM_NAME, RString::new(&arg.name));
+      hash.store(&*SYM_TYPE, CorvusType::new(arg.ty.clone()));
+      hash.store(&*SYM_OPTIONAL, Boolean::new(!arg.required));
+      hash.store(&*SYM_VARIADIC, Boolean::new(arg.variadic));
+      hash.to_any_object()
+    }).collect()
+  }
+
+  fn corvus_signature_return_type() -> AnyObject {
+    let signature: &Signature = itself.get_data(&*WRAPPER);
+    CorvusType::new(signature.get_return_type().clone())
+  }
+
+  fn corvus_signature_is_total() -> Boolean {
+    let signature: &Signature = itself.get_data(&*WRAPPER);
+    Boolean::new(signature.is_total())
+  }
+
+  fn corvus_signature_to_json_schema() -> Hash {
+    json_schema::signature_schema(itself.get_data(&*WRAPPER))
+  }
+
+  fn corvus_signature_metadata() -> AnyObject {
+    itself.instance_variable_get("@metadata")
+  }
 );
 
+impl CorvusSignature {
+  pub fn new(signature: Signature) -> AnyObject {
+    get_corvus_class!("Signature").wrap_data(signature, &*WRAPPER)
+  }
+
+  /// The signature of a function defined on a namespace, with its documentation.
+  pub fn with_metadata(signature: Signature, metadata: &Metadata) -> AnyObject {
+    let mut signature = CorvusSignature::new(signature);
+    signature.instance_variable_set("@metadata", metadata.to_ruby());
+    signature
+  }
+}
+
+/// Build a `Signature` from an array of `{name:, type:, optional:, variadic:, default:}`
+/// hashes. Arguments are required unless `optional` is truthy or a `default` is given.
 pub fn build_signature(
   args: Array,
   return_type: CorvusType,
   total: Boolean,
-) -> ::ruru::result::Result<Signature> {
+) -> ::ruru::result::Result<(Signature, ArgDefaults)> {
   let mut signature = Signature::with_capacity(args.length() as usize);
+  let mut defaults = ArgDefaults::new();
 
   signature.set_return_type(return_type.clone_type());
   signature.set_total(total.to_bool());
@@ -42,18 +100,54 @@ pub fn build_signature(
     let hash: Hash = arg.try_convert_to()?;
     let name: RString = hash.at(&*SYM_NAME).try_convert_to()?;
     let ty: CorvusType = hash.at(&*SYM_TYPE).try_convert_to()?;
+    let variadic = truthy(hash.at(&*SYM_VARIADIC));
+    let default = hash.at(&*SYM_DEFAULT);
+    let has_default = !default.is_nil();
+
+    if has_default {
+      if variadic {
+        return Err(Error::ArgumentError(format!(
+          "variadic argument `{}` cannot have a default",
+          name.to_str()
+        )));
+      }
+      let default = CorvusValue::from(default);
+      check_default(name.to_str(), &ty.clone_type(), &default)?;
+      defaults.insert(name.to_string(), default);
+    }
+
     signature.add_argument(Argument {
       name: name.to_string(),
       ty: ty.clone_type(),
-      required: truthy(hash.at(&*SYM_OPTIONAL)),
-      variadic: truthy(hash.at(&*SYM_VARIADIC)),
+      required: !(has_default || truthy(hash.at(&*SYM_OPTIONAL))),
+      variadic: variadic,
     })
   }
-  Ok(signature)
+  Ok((signature, defaults))
+}
+
+/// Defaults are checked like callback results, so e.g. Integers are Numbers.
+fn check_default(name: &str, ty: &Type, default: &CorvusValue) -> ::ruru::result::Result<()> {
+  let errors = value_check::check(ty, default, true);
+  if errors.is_empty() {
+    return Ok(());
+  }
+  let messages: Vec<String> = errors.iter().map(|e| format!("{}", e)).collect();
+  Err(Error::ArgumentError(format!(
+    "default for `{}` is not a valid {}: {}",
+    name,
+    ty,
+    messages.join(", ")
+  )))
 }
 
 pub fn init() {
   init_corvus_class!("Signature", |class| {
     class.def_self("new", corvus_signature_new);
+    class.def("arguments", corvus_signature_arguments);
+    class.def("return_type", corvus_signature_return_type);
+    class.def("total?", corvus_signature_is_total);
+    class.def("metadata", corvus_signature_metadata);
+    class.def("to_json_schema", corvus_signature_to_json_schema);
   });
 }
@@ -1,12 +1,28 @@
-use ruru::{AnyObject, Array, Boolean, Class, Hash, Object, RString, Symbol};
+use ruru::{AnyObject, Array, Boolean, Class, Hash, NilClass, Object, RString, Symbol};
 use corvus_core::{RecordField, Type};
 use helpers::raise_and_return_nil;
+use json_schema;
+use type_syntax;
+use unify;
+use value_check;
 
 lazy_static!(
   static ref SYM_TYPE: Symbol = Symbol::new("type");
-  static ref SYM_OPTIONAL: Symbol = Symbol::new("required");
+  static ref SYM_OPTIONAL: Symbol = Symbol::new("optional");
   static ref SYM_INPUTS: Symbol = Symbol::new("inputs");
   static ref SYM_OUTPUT: Symbol = Symbol::new("output");
+  static ref SYM_KIND: Symbol = Symbol::new("kind");
+  static ref SYM_OPEN: Symbol = Symbol::new("open");
+  static ref SYM_FIELDS: Symbol = Symbol::new("fields");
+  static ref SYM_ELEMENT: Symbol = Symbol::new("element");
+  static ref SYM_NAME: Symbol = Symbol::new("name");
+  static ref SYM_SUBSTITUTIONS: Symbol = Symbol::new("substitutions");
+  static ref SYM_MISMATCH: Symbol = Symbol::new("mismatch");
+  static ref SYM_PATH: Symbol = Symbol::new("path");
+  static ref SYM_EXPECTED: Symbol = Symbol::new("expected");
+  static ref SYM_ACTUAL: Symbol = Symbol::new("actual");
+  static ref SYM_MESSAGE: Symbol = Symbol::new("message");
+  static ref SYM_EXCERPT: Symbol = Symbol::new("excerpt");
 );
 
 wrappable_struct!(Type, TypeWrapper, WRAPPER);
@@ -17,10 +33,12 @@ methods!(
     CorvusType,
     itself,
 
-    fn corvus_type_self_record(fields: Hash) -> AnyObject {
+    fn corvus_type_self_build_record(fields: Hash, exact: AnyObject) -> AnyObject {
         use std::collections::HashMap;
         use ruru::result::Error;
+        use helpers::truthy;
 
+        let partial = !exact.map(truthy).unwrap_or(false);
         fields.and_then(|fields| {
             let mut field_types: HashMap<String, RecordField> = HashMap::new();
             let mut conversion_errors: Vec<Error> = vec![];
@@ -40,7 +58,7 @@ methods!(
             });
 
             if conversion_errors.is_empty() {
-                Ok(get_corvus_class!("Type").wrap_data(Type::Record(true, field_types), &*WRAPPER))
+                Ok(get_corvus_class!("Type").wrap_data(Type::Record(partial, field_types), &*WRAPPER))
             } else {
                 Err(conversion_errors.remove(0))
             }
@@ -74,6 +92,40 @@ methods!(
         }).unwrap_or_else(raise_and_return_nil)
     }
 
+    fn corvus_type_self_parse_expression(src: RString, named_types: Hash) -> AnyObject {
+        use std::collections::HashMap;
+        use ruru::result::Error;
+        use helpers::stringify_key;
+
+        Ok(()).and_then(|_| {
+            let src = src?;
+            let mut named: HashMap<String, Type> = HashMap::new();
+            let mut conversion_error = None;
+            named_types?.each(|name, ty| {
+                let named_type = stringify_key(name)
+                    .and_then(|name| ty.try_convert_to::<CorvusType>().map(|ty| (name, ty.clone_type())));
+                match named_type {
+                    Ok((name, ty)) => { named.insert(name, ty); }
+                    Err(err) => conversion_error = Some(err),
+                }
+            });
+            if let Some(err) = conversion_error {
+                return Err(err);
+            }
+            type_syntax::parse(src.to_str(), &named)
+                .map(CorvusType::new)
+                .map_err(|err| Error::ArgumentError(format!("{}", err)))
+        }).unwrap_or_else(raise_and_return_nil)
+    }
+
+    fn corvus_type_self_import_json_schema(schema: AnyObject) -> AnyObject {
+        schema.and_then(json_schema::from_schema).map(CorvusType::new).unwrap_or_else(raise_and_return_nil)
+    }
+
+    fn corvus_type_to_json_schema() -> Hash {
+        json_schema::to_schema(itself.get_data(&*WRAPPER))
+    }
+
     fn corvus_type_fields() -> Hash {
         let ty: &Type = itself.get_data(&*WRAPPER);
         let mut hash: Hash = Hash::new();
@@ -85,6 +137,78 @@ methods!(
         hash
     }
 
+    fn corvus_type_kind() -> Symbol {
+        Symbol::new(kind(itself.get_data(&*WRAPPER)))
+    }
+
+    fn corvus_type_element_type() -> AnyObject {
+        match *itself.get_data(&*WRAPPER) {
+            Type::List(ref inner) => CorvusType::new((**inner).clone()),
+            _ => NilClass::new().to_any_object(),
+        }
+    }
+
+    fn corvus_type_inputs() -> AnyObject {
+        match *itself.get_data(&*WRAPPER) {
+            Type::Block(ref inputs, _) => {
+                let inputs: Array = inputs.iter().map(|input| CorvusType::new(input.clone())).collect();
+                inputs.to_any_object()
+            }
+            _ => NilClass::new().to_any_object(),
+        }
+    }
+
+    fn corvus_type_output() -> AnyObject {
+        match *itself.get_data(&*WRAPPER) {
+            Type::Block(_, ref output) => CorvusType::new((**output).clone()),
+            _ => NilClass::new().to_any_object(),
+        }
+    }
+
+    fn corvus_type_var_name() -> AnyObject {
+        let ty: &Type = itself.get_data(&*WRAPPER);
+        if kind(ty) == "var" {
+            RString::new(&type_syntax::format(ty)).to_any_object()
+        } else {
+            NilClass::new().to_any_object()
+        }
+    }
+
+    fn corvus_type_optional_fields() -> Array {
+        field_names(itself.get_data(&*WRAPPER), true)
+    }
+
+    fn corvus_type_required_fields() -> Array {
+        field_names(itself.get_data(&*WRAPPER), false)
+    }
+
+    fn corvus_type_to_h() -> Hash {
+        to_hash(itself.get_data(&*WRAPPER))
+    }
+
+    fn corvus_type_assign_to(other: CorvusType) -> AnyObject {
+        other.map(|other| {
+            let to = other.clone_type();
+            let result = unify::assignable(itself.get_data(&*WRAPPER), &to)
+                .map(|subst| (unify::substitute(&to, &subst), subst));
+            unification_hash(result).to_any_object()
+        }).unwrap_or_else(raise_and_return_nil)
+    }
+
+    fn corvus_type_unify_with(other: CorvusType) -> AnyObject {
+        other.map(|other| {
+            unification_hash(unify::unify(itself.get_data(&*WRAPPER), &other.clone_type())).to_any_object()
+        }).unwrap_or_else(raise_and_return_nil)
+    }
+
+    fn corvus_type_is_open() -> Boolean {
+        let ty: &Type = itself.get_data(&*WRAPPER);
+        Boolean::new(match *ty {
+            Type::Record(partial, _) => partial,
+            _ => false,
+        })
+    }
+
     fn corvus_type_eq(other: AnyObject) -> Boolean {
         Boolean::new(match other.and_then(|ao| ao.try_convert_to::<CorvusType>()) {
             Ok(other) => other.get_data(&*WRAPPER) == itself.get_data(&*WRAPPER),
@@ -94,27 +218,31 @@ methods!(
 
     fn corvus_type_to_s() -> RString {
         let ty: &Type = itself.get_data(&*WRAPPER);
-        RString::new(format!("{}", ty).as_str())
+        RString::new(type_syntax::format(ty).as_str())
     }
 
     fn corvus_type_inspect() -> RString {
         let ty: &Type = itself.get_data(&*WRAPPER);
-        RString::new(format!("<Corvus::Type \"{}\">", ty).as_str())
+        RString::new(format!("<Corvus::Type \"{}\">", type_syntax::format(ty)).as_str())
     }
 
-    fn corvus_type_check_value(value: AnyObject) -> AnyObject {
+    fn corvus_type_check_value_errors(value: AnyObject, all: AnyObject) -> AnyObject {
         use value::CorvusValue;
-        use std::iter::FromIterator;
-        value.map(CorvusValue::from).map(|value| {
+        use helpers::truthy;
+
+        Ok(()).and_then(|_| {
+            let value = CorvusValue::from(value?);
             let ty: &Type = itself.get_data(&*WRAPPER);
-            match ty.satisfied_by_value(&CorvusValue::from(value)) {
-                Ok(_) => Array::new().to_any_object(),
-                Err(errors) => {
-                    Array::from_iter(
-                        errors.into_iter().map(|e| RString::from(format!("{}", e)).to_any_object())
-                    ).to_any_object()
-                }
-            }
+            let errors: Array = value_check::check(ty, &value, truthy(all?)).into_iter().map(|error| {
+                let mut hash = Hash::new();
+                hash.store(&*SYM_PATH, RString::new(&error.path));
+                hash.store(&*SYM_EXPECTED, CorvusType::new(error.expected));
+                hash.store(&*SYM_ACTUAL, RString::new(&error.actual));
+                hash.store(&*SYM_EXCERPT, RString::new(&error.excerpt));
+                hash.store(&*SYM_MESSAGE, RString::new(&error.message));
+                hash.to_any_object()
+            }).collect();
+            Ok(errors.to_any_object())
         }).unwrap_or_else(raise_and_return_nil)
     }
 );
@@ -129,13 +257,108 @@ impl CorvusType {
     }
 }
 
+/// The name `Type#kind` returns for `ty`'s variant.
+fn kind(ty: &Type) -> &'static str {
+    match *ty {
+        Type::Any => "any",
+        Type::Num => "number",
+        Type::Str => "string",
+        Type::Bool => "bool",
+        Type::Time => "time",
+        Type::Money => "money",
+        Type::List(_) => "list",
+        Type::Record(_, _) => "record",
+        Type::Block(_, _) => "block",
+        Type::Var(_) => "var",
+    }
+}
+
+/// The sorted names of a record's optional or required fields, empty for other types.
+fn field_names(ty: &Type, optional: bool) -> Array {
+    let mut names: Vec<&String> = match *ty {
+        Type::Record(_, ref fields) => fields
+            .iter()
+            .filter(|&(_, field)| field.is_optional() == optional)
+            .map(|(name, _)| name)
+            .collect(),
+        _ => vec![],
+    };
+    names.sort();
+    names.into_iter().map(|name| RString::new(name).to_any_object()).collect()
+}
+
+/// A plain Ruby description of `ty`: `{kind:}` plus `open:` and
+/// `fields: {name => {type:, optional:}}` for records, `element:` for lists,
+/// `inputs:` and `output:` for blocks and `name:` for type variables.
+fn to_hash(ty: &Type) -> Hash {
+    let mut hash = Hash::new();
+    hash.store(&*SYM_KIND, Symbol::new(kind(ty)));
+    match *ty {
+        Type::List(ref inner) => {
+            hash.store(&*SYM_ELEMENT, to_hash(inner));
+        }
+        Type::Record(partial, ref fields) => {
+            let mut names: Vec<&String> = fields.keys().collect();
+            names.sort();
+            let mut field_hashes = Hash::new();
+            for name in names {
+                let field = &fields[name];
+                let mut field_hash = Hash::new();
+                field_hash.store(&*SYM_TYPE, to_hash(field.get_type()));
+                field_hash.store(&*SYM_OPTIONAL, Boolean::new(field.is_optional()));
+                field_hashes.store(RString::new(name), field_hash);
+            }
+            hash.store(&*SYM_OPEN, Boolean::new(partial));
+            hash.store(&*SYM_FIELDS, field_hashes);
+        }
+        Type::Block(ref inputs, ref output) => {
+            let inputs: Array = inputs.iter().map(|input| to_hash(input).to_any_object()).collect();
+            hash.store(&*SYM_INPUTS, inputs);
+            hash.store(&*SYM_OUTPUT, to_hash(output));
+        }
+        _ => if kind(ty) == "var" {
+            hash.store(&*SYM_NAME, RString::new(&type_syntax::format(ty)));
+        },
+    }
+    hash
+}
+
+/// `{type:, substitutions: {name => Type}, mismatch: nil}` on success, or
+/// `{type: nil, substitutions: {}, mismatch: {path:, expected:, actual:, message:}}`.
+fn unification_hash(result: Result<(Type, unify::Substitution), unify::Mismatch>) -> Hash {
+    let mut hash = Hash::new();
+    let mut substitutions = Hash::new();
+    match result {
+        Ok((ty, subst)) => {
+            for (name, bound) in subst {
+                substitutions.store(RString::new(&name), CorvusType::new(bound));
+            }
+            hash.store(&*SYM_TYPE, CorvusType::new(ty));
+            hash.store(&*SYM_MISMATCH, NilClass::new());
+        }
+        Err(mismatch) => {
+            let mut details = Hash::new();
+            details.store(&*SYM_PATH, RString::new(&mismatch.path));
+            details.store(&*SYM_EXPECTED, CorvusType::new(mismatch.expected));
+            details.store(&*SYM_ACTUAL, CorvusType::new(mismatch.actual));
+            details.store(&*SYM_MESSAGE, RString::new(&mismatch.message));
+            hash.store(&*SYM_TYPE, NilClass::new());
+            hash.store(&*SYM_MISMATCH, details);
+        }
+    }
+    hash.store(&*SYM_SUBSTITUTIONS, substitutions);
+    hash
+}
+
 pub fn init() {
     init_corvus_class!("Type", |class| {
         // type constructors
-        class.def_self("record", corvus_type_self_record);
+        class.def_self("build_record", corvus_type_self_build_record);
         class.def_self("var", corvus_type_self_var);
         class.def_self("block", corvus_type_self_block);
         class.def_self("list", corvus_type_self_list);
+        class.def_self("parse_expression", corvus_type_self_parse_expression);
+        class.def_self("import_json_schema", corvus_type_self_import_json_schema);
 
         // Primitive type constants
         for (const_name, ty) in vec![
@@ -144,6 +367,7 @@ pub fn init() {
             ("String", Type::Str),
             ("Bool", Type::Bool),
             ("Time", Type::Time),
+            ("Money", Type::Money),
         ] {
             let t: AnyObject = get_corvus_class!("Type").wrap_data(ty, &*WRAPPER);
             class.const_set(const_name, &t);
@@ -154,6 +378,18 @@ pub fn init() {
         class.def("==", corvus_type_eq);
         class.def("inspect", corvus_type_inspect);
         class.def("fields", corvus_type_fields);
-        class.def("check_value", corvus_type_check_value);
+        class.def("open?", corvus_type_is_open);
+        class.def("kind", corvus_type_kind);
+        class.def("element_type", corvus_type_element_type);
+        class.def("inputs", corvus_type_inputs);
+        class.def("output", corvus_type_output);
+        class.def("var_name", corvus_type_var_name);
+        class.def("optional_fields", corvus_type_optional_fields);
+        class.def("required_fields", corvus_type_required_fields);
+        class.def("to_h", corvus_type_to_h);
+        class.def("assign_to", corvus_type_assign_to);
+        class.def("unify_with", corvus_type_unify_with);
+        class.def("check_value_errors", corvus_type_check_value_errors);
+        class.def("to_json_schema", corvus_type_to_json_schema);
     });
 }
@@ -31,9 +31,12 @@ impl<'writer, W: io::Write> RubyEmitter<'writer, W> {
   }
 
   fn emit_method_definition(&mut self, stx: &Syntax) -> io::Result<()> {
-    write!(self.writer, "def call(**εε)\n")?;
+    // the context is made available to callbacks for the duration of the call;
+    // `Script#call` applies the output policy to the result
+    write!(self.writer, "def corvus_compiled_call(εcontext = nil, **εε)\n")?;
+    write!(self.writer, "self.corvus_enter(εcontext)\nbegin\n")?;
     self.emit(stx)?;
-    write!(self.writer, "\nend")
+    write!(self.writer, "\nensure\nself.corvus_leave\nend\nend")
   }
 
   fn emit(&mut self, stx: &Syntax) -> io::Result<()> {
@@ -43,7 +46,7 @@ impl<'writer, W: io::Write> RubyEmitter<'writer, W> {
         Prim::String(ref s) => write!(self.writer, "{:?}", s),
         Prim::Number(n) => write!(self.writer, "{:?}", n),
         Prim::Time(t) => write!(self.writer, "Time.at({:?})", t),
-        Prim::Money(ref c, ref a) => write!(self.writer, "Money.parse({:?} + {:?}.to_s)", c, a),
+        Prim::Money(ref c, ref a) => write!(self.writer, "Corvus::Money.parse({:?}, {:?}.to_s)", c, a),
       },
       Syntax::Block(ref arg_names, ref body) => {
         write!(self.writer, "Proc.new{{")?;
@@ -134,8 +137,9 @@ impl<'writer, W: io::Write> RubyEmitter<'writer, W> {
   }
 
   fn emit_math(&mut self, apply: &Apply<Syntax>) -> io::Result<()> {
-    // calc: 1 plus: 2 times: 3
-    // (((1+2)*3)-5)
+    // calc: 1 plus: 2 times: 3 dividedBy: 5
+    // ((((1)+2)*3).fdiv(5))
+    // fdiv so that Integer globals divide like the interpreter's Floats
     for _ in apply.iter() {
       write!(self.writer, "(")?;
     }
@@ -144,10 +148,13 @@ impl<'writer, W: io::Write> RubyEmitter<'writer, W> {
         "plus" => write!(self.writer, "+")?,
         "subtract" => write!(self.writer, "-")?,
         "times" => write!(self.writer, "*")?,
-        "dividedBy" => write!(self.writer, "/")?,
+        "dividedBy" => write!(self.writer, ".fdiv(")?,
         _ => (),
       }
       self.emit(val)?;
+      if op == "dividedBy" {
+        write!(self.writer, ")")?;
+      }
       write!(self.writer, ")")?;
     }
     Ok(())
@@ -204,6 +211,11 @@ mod tests {
     ruby_emit_eq!("{ x y => [x y] }", "Proc.new{|ε_x,ε_y|[ε_x, ε_y]}");
   }
 
+  #[test]
+  fn test_emit_math() {
+    ruby_emit_eq!("calc: 1 plus: 2 dividedBy: 4", "(((1.0)+2.0).fdiv(4.0))");
+  }
+
   #[test]
   fn test_emit_apply() {
     ruby_emit_eq!(
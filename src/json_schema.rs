@@ -0,0 +1,196 @@
+//! Conversion between Corvus types and JSON Schema (draft 7), for validating script
+//! inputs outside of Ruby.
+//!
+//! Schemas are Ruby Hashes with String keys, ready for `JSON.generate`. Blocks can't
+//! be described by JSON Schema, so they are exported as an `x-corvus-block`
+//! annotation that `from_schema` understands but validators ignore. Type variables
+//! allow any value, their name is kept in an `x-corvus-type` annotation.
+
+use std::collections::HashMap;
+
+use ruru::{AnyObject, Array, Boolean, Hash, Object, RString, Symbol};
+use ruru::result::{Error, Result};
+
+use corvus_core::{RecordField, Type};
+use corvus_core::signature::Signature;
+
+use helpers::{stringify_key, truthy};
+use type_syntax;
+
+const MONEY_PATTERN: &'static str = "^-?[0-9]+(\\.[0-9]+)? [A-Z]{3}$";
+
+pub fn to_schema(ty: &Type) -> Hash {
+  let mut schema = Hash::new();
+  match *ty {
+    Type::Any => {}
+    Type::Num => store(&mut schema, "type", RString::new("number")),
+    Type::Str => store(&mut schema, "type", RString::new("string")),
+    Type::Bool => store(&mut schema, "type", RString::new("boolean")),
+    Type::Time => {
+      store(&mut schema, "type", RString::new("string"));
+      store(&mut schema, "format", RString::new("date-time"));
+    }
+    // as formatted by Corvus::Money#to_s
+    Type::Money => {
+      store(&mut schema, "type", RString::new("string"));
+      store(&mut schema, "format", RString::new("money"));
+      store(&mut schema, "pattern", RString::new(MONEY_PATTERN));
+    }
+    Type::List(ref inner) => {
+      store(&mut schema, "type", RString::new("array"));
+      store(&mut schema, "items", to_schema(inner));
+    }
+    Type::Record(partial, ref fields) => {
+      let mut names: Vec<&String> = fields.keys().collect();
+      names.sort();
+      let mut properties = Hash::new();
+      let mut required = Array::new();
+      for name in names {
+        let field = &fields[name];
+        properties.store(RString::new(name), to_schema(field.get_type()));
+        if !field.is_optional() {
+          required.push(RString::new(name));
+        }
+      }
+      store(&mut schema, "type", RString::new("object"));
+      store(&mut schema, "properties", properties);
+      store(&mut schema, "required", required);
+      if !partial {
+        store(&mut schema, "additionalProperties", Boolean::new(false));
+      }
+    }
+    Type::Block(ref inputs, ref output) => {
+      let inputs: Array = inputs.iter().map(|input| to_schema(input).to_any_object()).collect();
+      let mut block = Hash::new();
+      store(&mut block, "inputs", inputs);
+      store(&mut block, "output", to_schema(output));
+      let description = format!("a Corvus block: {}", type_syntax::format(ty));
+      store(&mut schema, "description", RString::new(&description));
+      store(&mut schema, "x-corvus-block", block);
+    }
+    // type variables stand for any type
+    Type::Var(ref name) => store(&mut schema, "x-corvus-type", RString::new(name)),
+  }
+  schema
+}
+
+/// The schema of the arguments a function is called with, as an object keyed by
+/// argument name. The return type is an `x-corvus-returns` annotation.
+pub fn signature_schema(signature: &Signature) -> Hash {
+  let mut properties = Hash::new();
+  let mut required = Array::new();
+  for arg in signature.args() {
+    let ty = if arg.variadic { Type::list_of(arg.ty.clone()) } else { arg.ty.clone() };
+    properties.store(RString::new(&arg.name), to_schema(&ty));
+    if arg.required {
+      required.push(RString::new(&arg.name));
+    }
+  }
+  let mut schema = Hash::new();
+  store(&mut schema, "type", RString::new("object"));
+  store(&mut schema, "properties", properties);
+  store(&mut schema, "required", required);
+  store(&mut schema, "additionalProperties", Boolean::new(false));
+  store(&mut schema, "x-corvus-returns", to_schema(signature.get_return_type()));
+  store(&mut schema, "x-corvus-total", Boolean::new(signature.is_total()));
+  schema
+}
+
+/// Read a schema produced by `to_schema`, or any schema using only `type` (a single
+/// type name), `format: date-time`, `items`, `properties`, `required` and
+/// `additionalProperties: false`. Keys may be Strings or Symbols.
+pub fn from_schema(schema: AnyObject) -> Result<Type> {
+  let schema: Hash = schema.try_convert_to()?;
+
+  let block = at(&schema, "x-corvus-block");
+  if !block.is_nil() {
+    let block: Hash = block.try_convert_to()?;
+    let inputs: Array = at(&block, "inputs").try_convert_to()?;
+    let inputs = inputs.into_iter().map(from_schema).collect::<Result<Vec<Type>>>()?;
+    return Ok(Type::Block(inputs, Box::new(from_schema(at(&block, "output"))?)));
+  }
+
+  let var = at(&schema, "x-corvus-type");
+  if !var.is_nil() {
+    return Ok(Type::var(&stringify_key(var)?));
+  }
+
+  let type_name = at(&schema, "type");
+  if type_name.is_nil() {
+    return Ok(Type::Any);
+  }
+  let type_name = stringify_key(type_name).map_err(|_| {
+    Error::ArgumentError("only a single JSON Schema `type` is supported".to_string())
+  })?;
+  match type_name.as_str() {
+    "number" | "integer" => Ok(Type::Num),
+    "boolean" => Ok(Type::Bool),
+    "string" => {
+      let format = at(&schema, "format");
+      let format = if format.is_nil() { String::new() } else { stringify_key(format)? };
+      match format.as_str() {
+        "date-time" => Ok(Type::Time),
+        "money" => Ok(Type::Money),
+        _ => Ok(Type::Str),
+      }
+    }
+    "array" => {
+      let items = at(&schema, "items");
+      let inner = if items.is_nil() { Type::Any } else { from_schema(items)? };
+      Ok(Type::list_of(inner))
+    }
+    "object" => record_from_schema(&schema),
+    other => Err(Error::ArgumentError(format!(
+      "JSON Schema type `{}` has no Corvus equivalent",
+      other
+    ))),
+  }
+}
+
+fn record_from_schema(schema: &Hash) -> Result<Type> {
+  let required = at(schema, "required");
+  let required: Vec<String> = if required.is_nil() {
+    vec![]
+  } else {
+    let required: Array = required.try_convert_to()?;
+    required.into_iter().map(stringify_key).collect::<Result<Vec<String>>>()?
+  };
+
+  let mut fields: HashMap<String, RecordField> = HashMap::new();
+  let properties = at(schema, "properties");
+  if !properties.is_nil() {
+    let properties: Hash = properties.try_convert_to()?;
+    let mut conversion_error = None;
+    properties.each(|name, property| {
+      let field = stringify_key(name).and_then(|name| from_schema(property).map(|ty| (name, ty)));
+      match field {
+        Ok((name, ty)) => {
+          let optional = !required.contains(&name);
+          fields.insert(name, RecordField::new(ty, optional));
+        }
+        Err(err) => conversion_error = Some(err),
+      }
+    });
+    if let Some(err) = conversion_error {
+      return Err(err);
+    }
+  }
+
+  let additional = at(schema, "additionalProperties");
+  let partial = additional.is_nil() || truthy(additional);
+  Ok(Type::Record(partial, fields))
+}
+
+fn store<T: Object>(hash: &mut Hash, key: &str, value: T) {
+  hash.store(RString::new(key), value);
+}
+
+/// `hash[key]`, or `hash[key.to_sym]` for schemas written in Ruby.
+fn at(hash: &Hash, key: &str) -> AnyObject {
+  let value = hash.at(&RString::new(key));
+  if value.is_nil() {
+    hash.at(&Symbol::new(key))
+  } else {
+    value
+  }
+}
@@ -9,6 +9,17 @@ extern crate ruru;
 
 mod helpers;
 mod emitter;
+mod callback;
+mod capabilities;
+mod json_schema;
+mod metadata;
+mod namespace_layer;
+mod prelude;
+mod ruby_calc;
+mod syntax;
+mod type_syntax;
+mod unify;
+mod value_check;
 
 pub mod error;
 pub mod value;
@@ -0,0 +1,160 @@
+//! Documentation attached to functions defined from Ruby
+
+use ruru::{AnyObject, Array, Hash, NilClass, Object, RString, Symbol};
+use ruru::result::Result;
+
+use helpers::{stringify_key, truthy};
+
+lazy_static!(
+  static ref SYM_NAME: Symbol = Symbol::new("name");
+  static ref SYM_DOC: Symbol = Symbol::new("doc");
+  static ref SYM_DESCRIPTION: Symbol = Symbol::new("description");
+  static ref SYM_EXAMPLES: Symbol = Symbol::new("examples");
+  static ref SYM_CATEGORY: Symbol = Symbol::new("category");
+  static ref SYM_DEPRECATED: Symbol = Symbol::new("deprecated");
+  static ref SYM_ARGUMENTS: Symbol = Symbol::new("arguments");
+  static ref SYM_MESSAGE: Symbol = Symbol::new("message");
+  static ref SYM_REPLACEMENT: Symbol = Symbol::new("replacement");
+);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+  pub description: Option<String>,
+  /// `(argument name, doc)` in argument order
+  pub argument_docs: Vec<(String, String)>,
+  /// Corvus source snippets showing how to call the function
+  pub examples: Vec<String>,
+  pub category: Option<String>,
+  pub deprecation: Option<Deprecation>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Deprecation {
+  pub message: Option<String>,
+  /// The function to call instead
+  pub replacement: Option<String>,
+}
+
+impl Metadata {
+  /// Read metadata from the arguments of `Namespace#define`: a `doc:` in each
+  /// argument Hash, and `description:`, `examples:`, `category:` and `deprecated:` in
+  /// `options`. `deprecated:` is `true`, a message, or a Hash with `message:` and
+  /// `replacement:`.
+  pub fn from_ruby(args: &Array, options: &Hash) -> Result<Metadata> {
+    let mut argument_docs = vec![];
+    for arg in args.clone() {
+      let arg: Hash = arg.try_convert_to()?;
+      if let Some(doc) = optional_string(arg.at(&*SYM_DOC))? {
+        argument_docs.push((stringify_key(arg.at(&*SYM_NAME))?, doc));
+      }
+    }
+
+    let examples = options.at(&*SYM_EXAMPLES);
+    let examples = if examples.is_nil() {
+      vec![]
+    } else {
+      let examples: Array = examples.try_convert_to()?;
+      examples
+        .into_iter()
+        .map(|example| example.try_convert_to::<RString>().map(|s| s.to_string()))
+        .collect::<Result<Vec<String>>>()?
+    };
+
+    Ok(Metadata {
+      description: optional_string(options.at(&*SYM_DESCRIPTION))?,
+      argument_docs: argument_docs,
+      examples: examples,
+      category: optional_string(options.at(&*SYM_CATEGORY))?,
+      deprecation: deprecation(options.at(&*SYM_DEPRECATED))?,
+    })
+  }
+
+  pub fn argument_doc(&self, name: &str) -> Option<&str> {
+    self
+      .argument_docs
+      .iter()
+      .find(|&&(ref arg, _)| arg == name)
+      .map(|&(_, ref doc)| doc.as_str())
+  }
+
+  /// A warning for scripts that call the function `name`, if it's deprecated.
+  pub fn deprecation_warning(&self, name: &str) -> Option<String> {
+    self.deprecation.as_ref().map(|deprecation| {
+      let mut warning = format!("`{}` is deprecated", name);
+      if let Some(ref replacement) = deprecation.replacement {
+        warning.push_str(&format!(", use `{}` instead", replacement));
+      }
+      if let Some(ref message) = deprecation.message {
+        warning.push_str(&format!(": {}", message));
+      }
+      warning
+    })
+  }
+
+  /// The metadata as `{description:, arguments: {name => doc}, examples:, category:,
+  /// deprecated: {message:, replacement:}}`, with nil for missing values.
+  pub fn to_ruby(&self) -> Hash {
+    let mut arguments = Hash::new();
+    for &(ref name, ref doc) in &self.argument_docs {
+      arguments.store(RString::new(name), RString::new(doc));
+    }
+    let examples: Array = self
+      .examples
+      .iter()
+      .map(|example| RString::new(example).to_any_object())
+      .collect();
+    let deprecated = match self.deprecation {
+      Some(ref deprecation) => {
+        let mut hash = Hash::new();
+        hash.store(&*SYM_MESSAGE, string_or_nil(&deprecation.message));
+        hash.store(&*SYM_REPLACEMENT, string_or_nil(&deprecation.replacement));
+        hash.to_any_object()
+      }
+      None => NilClass::new().to_any_object(),
+    };
+
+    let mut hash = Hash::new();
+    hash.store(&*SYM_DESCRIPTION, string_or_nil(&self.description));
+    hash.store(&*SYM_ARGUMENTS, arguments);
+    hash.store(&*SYM_EXAMPLES, examples);
+    hash.store(&*SYM_CATEGORY, string_or_nil(&self.category));
+    hash.store(&*SYM_DEPRECATED, deprecated);
+    hash
+  }
+}
+
+fn deprecation(value: AnyObject) -> Result<Option<Deprecation>> {
+  if !truthy(value.clone()) {
+    return Ok(None);
+  }
+  if value.value().is_true() {
+    return Ok(Some(Deprecation::default()));
+  }
+  if let Ok(message) = value.try_convert_to::<RString>() {
+    return Ok(Some(Deprecation {
+      message: Some(message.to_string()),
+      replacement: None,
+    }));
+  }
+  let hash: Hash = value.try_convert_to()?;
+  Ok(Some(Deprecation {
+    message: optional_string(hash.at(&*SYM_MESSAGE))?,
+    replacement: optional_string(hash.at(&*SYM_REPLACEMENT))?,
+  }))
+}
+
+/// A String or Symbol as a String, nil as None.
+fn optional_string(value: AnyObject) -> Result<Option<String>> {
+  if value.is_nil() {
+    Ok(None)
+  } else {
+    stringify_key(value).map(Some)
+  }
+}
+
+fn string_or_nil(value: &Option<String>) -> AnyObject {
+  match *value {
+    Some(ref s) => RString::new(s).to_any_object(),
+    None => NilClass::new().to_any_object(),
+  }
+}
@@ -0,0 +1,665 @@
+//! The functions defined on a `Corvus::Namespace` from Ruby.
+//!
+//! Each namespace keeps only its own definitions; a fork looks up anything else in
+//! its parent. The corvus_core namespace that scripts run against is built from the
+//! visible signatures when it's first needed after a change, and its entries
+//! forward calls to whatever the name resolves to at the time, so forking and
+//! defining never copy or rebuild anything.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::iter::empty;
+use std::rc::{Rc, Weak};
+
+use corvus_core::{type_of, Apply, INamespace, Namespace, SharedNamespace, Syntax};
+use corvus_core::signature::{Argument, Signature};
+
+use callback::function_name;
+use classes::corvus_namespace::Settings;
+use error::Error;
+use metadata::Metadata;
+use prelude::{module_of, Prelude};
+use ruby_calc;
+use syntax::function_names;
+use type_syntax;
+use value::CorvusValue;
+
+/// A function defined from Ruby. It is called with the settings of the namespace the
+/// call was made through, which may be a fork of the one it was defined on.
+pub type Callback = Rc<Fn(Apply<CorvusValue>, &Settings) -> Result<CorvusValue, Error>>;
+
+#[derive(Clone)]
+pub struct Definition {
+  pub name: String,
+  pub signature: Signature,
+  pub callback: Callback,
+  /// Capability groups scripts must be granted to call the function.
+  pub capabilities: Vec<String>,
+  pub metadata: Metadata,
+  /// Set for aliases, whose signature is derived from the function they call.
+  pub alias_of: Option<Rc<AliasOf>>,
+}
+
+pub struct AliasOf {
+  pub original: String,
+  /// Original argument name => alias argument name, for renamed arguments.
+  pub argument_names: HashMap<String, String>,
+}
+
+impl Definition {
+  pub fn new(signature: Signature, callback: Callback) -> Self {
+    Definition {
+      name: function_name(&signature),
+      signature: signature,
+      callback: callback,
+      capabilities: vec![],
+      metadata: Metadata::default(),
+      alias_of: None,
+    }
+  }
+}
+
+/// A script compiled against a namespace, kept so that redefinitions can be checked
+/// against the scripts they would affect.
+pub struct CompiledScript {
+  pub source: String,
+  pub stx: Syntax,
+}
+
+pub struct NamespaceLayer {
+  pub settings: Rc<Settings>,
+  pub prelude: Prelude,
+  parent: Option<Rc<NamespaceLayer>>,
+  local: RefCell<Vec<Rc<Definition>>>,
+  /// Bumped whenever `local` changes.
+  generation: Cell<u64>,
+  /// The core namespace, and the `version` it was built at.
+  core: RefCell<Option<(u64, SharedNamespace<CorvusValue>)>>,
+  children: RefCell<Vec<Weak<NamespaceLayer>>>,
+  scripts: RefCell<Vec<Weak<CompiledScript>>>,
+}
+
+impl NamespaceLayer {
+  pub fn root(settings: Settings, prelude: Prelude) -> Rc<Self> {
+    Rc::new(NamespaceLayer::new(Rc::new(settings), prelude, None))
+  }
+
+  /// A child that sees every definition of `parent`, including ones made after the
+  /// fork, unless it overrides them.
+  pub fn fork(parent: &Rc<Self>, settings: Settings) -> Rc<Self> {
+    let child = Rc::new(NamespaceLayer::new(
+      Rc::new(settings),
+      parent.prelude.clone(),
+      Some(parent.clone()),
+    ));
+    let mut children = parent.children.borrow_mut();
+    children.retain(|child| child.upgrade().is_some());
+    children.push(Rc::downgrade(&child));
+    child
+  }
+
+  fn new(settings: Rc<Settings>, prelude: Prelude, parent: Option<Rc<NamespaceLayer>>) -> Self {
+    NamespaceLayer {
+      settings: settings,
+      prelude: prelude,
+      parent: parent,
+      local: RefCell::new(vec![]),
+      generation: Cell::new(0),
+      core: RefCell::new(None),
+      children: RefCell::new(vec![]),
+      scripts: RefCell::new(vec![]),
+    }
+  }
+
+  /// The core namespace scripts are parsed, type checked and run against: the
+  /// prelude plus an entry for every visible definition, which calls whatever the
+  /// name resolves to when it's called. It is built on first use after a change
+  /// to this namespace or one of its ancestors; scripts that are already running
+  /// keep the one they started with.
+  pub fn ns(layer: &Rc<Self>) -> Result<SharedNamespace<CorvusValue>, String> {
+    let version = layer.version();
+    if let Some((built_at, ref ns)) = *layer.core.borrow() {
+      if built_at == version {
+        return Ok(ns.clone());
+      }
+    }
+    let ns = fresh_namespace()?;
+    for definition in layer.definitions() {
+      insert_forwarder(&ns, layer, &definition)?;
+    }
+    ruby_calc::insert(&ns)?;
+    *layer.core.borrow_mut() = Some((version, ns.clone()));
+    Ok(ns)
+  }
+
+  /// Changes whenever the definitions visible here do.
+  pub fn version(&self) -> u64 {
+    // generations only grow, so the sum changes whenever any of them does
+    self.generation.get() + self.parent.as_ref().map_or(0, |parent| parent.version())
+  }
+
+  /// The callback visible as `name`, falling through to the parent.
+  fn callback_of(&self, name: &str) -> Option<Callback> {
+    self
+      .local
+      .borrow()
+      .iter()
+      .find(|definition| definition.name == name)
+      .map(|definition| definition.callback.clone())
+      .or_else(|| self.parent.as_ref().and_then(|parent| parent.callback_of(name)))
+  }
+
+  /// The definition visible as `name`, falling through to the parent.
+  pub fn lookup(&self, name: &str) -> Option<Rc<Definition>> {
+    self
+      .local
+      .borrow()
+      .iter()
+      .find(|definition| definition.name == name)
+      .cloned()
+      .or_else(|| self.parent.as_ref().and_then(|parent| parent.lookup(name)))
+  }
+
+  /// Every visible definition, in the order they were first defined.
+  pub fn definitions(&self) -> Vec<Rc<Definition>> {
+    let mut definitions = self
+      .parent
+      .as_ref()
+      .map(|parent| parent.definitions())
+      .unwrap_or_default();
+    for definition in self.local.borrow().iter() {
+      match definitions.iter().position(|d| d.name == definition.name) {
+        Some(i) => definitions[i] = definition.clone(),
+        None => definitions.push(definition.clone()),
+      }
+    }
+    definitions
+  }
+
+  /// Whether scripts may call `name`: it's defined from Ruby, or in the prelude
+  /// modules this namespace was created with.
+  pub fn allows(&self, name: &str) -> bool {
+    self.lookup(name).is_some() || self.prelude.allows(name)
+  }
+
+  /// Fail with an error naming the first of `names` that scripts may not call.
+  pub fn check_allowed<'a, I>(&self, names: I) -> Result<(), String>
+  where
+    I: IntoIterator<Item = &'a String>,
+  {
+    match names.into_iter().find(|name| !self.allows(name)) {
+      Some(name) => Err(format!("`{}` is not available in this namespace", name)),
+      None => Ok(()),
+    }
+  }
+
+  /// The capability groups of the function `name`.
+  pub fn capabilities_of(&self, name: &str) -> Vec<String> {
+    match self.lookup(name) {
+      Some(definition) => definition.capabilities.clone(),
+      None => vec![module_of(name).to_string()],
+    }
+  }
+
+  /// Deprecation warnings for the functions in `names`, in order.
+  pub fn deprecation_warnings<'a, I>(&self, names: I) -> Vec<String>
+  where
+    I: IntoIterator<Item = &'a String>,
+  {
+    names
+      .into_iter()
+      .filter_map(|name| {
+        self
+          .lookup(name)
+          .and_then(|definition| definition.metadata.deprecation_warning(name))
+      })
+      .collect()
+  }
+
+  /// The names of the callable functions, prelude first.
+  pub fn function_names(&self) -> Vec<String> {
+    let mut names: Vec<String> = self.prelude.function_names().into_iter().map(String::from).collect();
+    names.extend(self.definitions().into_iter().map(|definition| definition.name.clone()));
+    names
+  }
+
+  /// The signature and metadata of every callable function, prelude first. Prelude
+  /// functions have no documentation, their category is their prelude module.
+  pub fn signatures(layer: &Rc<Self>) -> Result<Vec<(Signature, Metadata)>, String> {
+    let ns = NamespaceLayer::ns(layer)?;
+    let ns = ns.borrow();
+    let mut signatures: Vec<(Signature, Metadata)> = layer
+      .prelude
+      .function_names()
+      .into_iter()
+      .filter_map(|name| {
+        ns.get_signature(name).map(|signature| {
+          let metadata = Metadata {
+            category: Some(module_of(name).to_string()),
+            ..Metadata::default()
+          };
+          (signature.clone(), metadata)
+        })
+      })
+      .collect();
+    signatures.extend(
+      layer
+        .definitions()
+        .into_iter()
+        .map(|definition| (definition.signature.clone(), definition.metadata.clone())),
+    );
+    Ok(signatures)
+  }
+
+  /// Identifies what scripts compiled against this namespace can call and how, for
+  /// keying caches of compiled scripts: it changes whenever a function is defined,
+  /// removed, or redefined with a different signature or capabilities.
+  pub fn cache_key(&self) -> String {
+    let prelude = match self.prelude {
+      Prelude::All => "*".to_string(),
+      Prelude::Only(ref modules) => modules.join(","),
+    };
+    let mut definitions: Vec<String> = self
+      .definitions()
+      .iter()
+      .map(|definition| definition_key(definition))
+      .collect();
+    definitions.sort();
+    format!("prelude={};functions={}", prelude, definitions.join(";"))
+  }
+
+  /// Define a function, or override an inherited one.
+  pub fn define(&self, definition: Definition) -> Result<(), String> {
+    if self.is_local(&definition.name) {
+      return Err(format!("`{}` is already defined", definition.name));
+    }
+    check_insertable(&definition)?;
+    let name = definition.name.clone();
+    self.local.borrow_mut().push(Rc::new(definition));
+    self.changed();
+    // aliases of an inherited function now call this one
+    self.refresh_aliases(&name);
+    Ok(())
+  }
+
+  /// Remove a function defined on this namespace. If it overrode an inherited
+  /// function, the inherited one becomes visible again, otherwise its aliases must
+  /// be undefined first.
+  pub fn undefine(&self, name: &str) -> Result<(), String> {
+    if !self.is_local(name) {
+      return Err(match self.lookup(name) {
+        Some(_) => format!("`{}` is inherited, undefine it on the parent namespace", name),
+        None => format!("`{}` is not defined", name),
+      });
+    }
+    let inherited = self.parent.as_ref().map_or(false, |parent| parent.lookup(name).is_some());
+    if !inherited {
+      let aliases = self.aliases_of(name);
+      if !aliases.is_empty() {
+        return Err(format!(
+          "`{}` is aliased as `{}`, undefine the aliases first",
+          name,
+          aliases.join("`, `")
+        ));
+      }
+    }
+    self.local.borrow_mut().retain(|definition| definition.name != name);
+    self.changed();
+    if inherited {
+      self.refresh_aliases(name);
+    }
+    Ok(())
+  }
+
+  /// Replace the visible definition of a function. Use `redefinition_problems` first
+  /// to find out what that would break.
+  pub fn redefine(&self, definition: Definition) -> Result<(), String> {
+    if self.lookup(&definition.name).is_none() {
+      return Err(format!("`{}` is not defined", definition.name));
+    }
+    check_insertable(&definition)?;
+    let name = definition.name.clone();
+    {
+      let mut local = self.local.borrow_mut();
+      match local.iter().position(|d| d.name == definition.name) {
+        Some(i) => local[i] = Rc::new(definition),
+        None => local.push(Rc::new(definition)),
+      }
+    }
+    self.changed();
+    self.refresh_aliases(&name);
+    Ok(())
+  }
+
+  /// How replacing the current definition with `definition` would break callers:
+  /// incompatible signature changes, and live compiled scripts that would no
+  /// longer type check.
+  pub fn redefinition_problems(&self, definition: &Definition) -> Result<Vec<String>, String> {
+    let current = self
+      .lookup(&definition.name)
+      .ok_or_else(|| format!("`{}` is not defined", definition.name))?;
+    let mut problems = signature_problems(&current.signature, &definition.signature);
+    self.script_problems(&[definition.clone()], &mut problems)?;
+    Ok(problems)
+  }
+
+  /// Type check the live scripts of this namespace and its forks that call the
+  /// first of `replacements` or one of its aliases as if it had been redefined.
+  /// `replacements` also holds the aliases from the namespaces in between, derived
+  /// from the redefinition.
+  fn script_problems(&self, replacements: &[Definition], problems: &mut Vec<String>) -> Result<(), String> {
+    let replacements = self.with_local_aliases(replacements);
+    let scripts: Vec<Rc<CompiledScript>> = self
+      .scripts
+      .borrow()
+      .iter()
+      .filter_map(|script| script.upgrade())
+      .filter(|script| {
+        let called = function_names(&script.stx);
+        replacements.iter().any(|replacement| called.contains(&replacement.name))
+      })
+      .collect();
+
+    if !scripts.is_empty() {
+      let ns = fresh_namespace()?;
+      for existing in self.definitions() {
+        let replacement = replacements.iter().find(|replacement| replacement.name == existing.name);
+        insert_into(&ns, replacement.unwrap_or(&*existing), &self.settings)?;
+      }
+      let ns = ns.borrow();
+      for script in scripts {
+        if let Err(err) = type_of(&*ns, empty(), &script.stx) {
+          problems.push(format!("script `{}` would no longer compile: {}", script.source, err));
+        }
+      }
+    }
+
+    for child in self.live_children() {
+      // forks that override the function are unaffected
+      if !child.is_local(&replacements[0].name) {
+        child.script_problems(&replacements, problems)?;
+      }
+    }
+    Ok(())
+  }
+
+  /// `replacements` and, derived from them, the aliases of them defined here.
+  fn with_local_aliases(&self, replacements: &[Definition]) -> Vec<Definition> {
+    let mut replacements = replacements.to_vec();
+    let mut i = 0;
+    while i < replacements.len() {
+      let original = replacements[i].clone();
+      for existing in self.local.borrow().iter() {
+        if let Some(ref alias_of) = existing.alias_of {
+          if alias_of.original == original.name {
+            let callback = existing.callback.clone();
+            let alias = alias_definition(&existing.name, &original, &alias_of.argument_names, callback);
+            replacements.push(alias);
+          }
+        }
+      }
+      i += 1;
+    }
+    replacements
+  }
+
+  /// Derive the aliases of `name` again from what `name` now resolves to, here and
+  /// in the forks that don't override it, so that their signatures match the
+  /// function they call.
+  fn refresh_aliases(&self, name: &str) {
+    let original = match self.lookup(name) {
+      Some(original) => original,
+      None => return,
+    };
+    let mut refreshed = vec![];
+    for definition in self.local.borrow_mut().iter_mut() {
+      let alias_of = match definition.alias_of {
+        Some(ref alias_of) if alias_of.original == name => alias_of.clone(),
+        _ => continue,
+      };
+      let callback = definition.callback.clone();
+      let alias = alias_definition(&definition.name, &original, &alias_of.argument_names, callback);
+      *definition = Rc::new(alias);
+      refreshed.push(definition.name.clone());
+    }
+    if !refreshed.is_empty() {
+      self.changed();
+    }
+    for alias in refreshed {
+      self.refresh_aliases(&alias);
+    }
+    for child in self.live_children() {
+      if !child.is_local(name) {
+        child.refresh_aliases(name);
+      }
+    }
+  }
+
+  /// The aliases that call `name` as it's defined here.
+  fn aliases_of(&self, name: &str) -> Vec<String> {
+    let mut aliases: Vec<String> = self
+      .local
+      .borrow()
+      .iter()
+      .filter(|definition| definition.alias_of.as_ref().map_or(false, |alias_of| alias_of.original == name))
+      .map(|definition| definition.name.clone())
+      .collect();
+    for child in self.live_children() {
+      if !child.is_local(name) {
+        aliases.extend(child.aliases_of(name));
+      }
+    }
+    aliases
+  }
+
+  fn live_children(&self) -> Vec<Rc<NamespaceLayer>> {
+    self.children.borrow().iter().filter_map(|child| child.upgrade()).collect()
+  }
+
+  /// Define `alias_name` as another name for the function `original`, renaming its
+  /// other arguments according to `argument_names` (original name => alias). Calls
+  /// go to whatever `original` is defined as at the time, and the alias's signature
+  /// follows when `original` is redefined.
+  pub fn alias(
+    layer: &Rc<Self>,
+    alias_name: &str,
+    original: &str,
+    argument_names: &HashMap<String, String>,
+  ) -> Result<(), String> {
+    let definition = layer.lookup(original).ok_or_else(|| {
+      format!("`{}` is not defined from Ruby, so it can't be aliased", original)
+    })?;
+    for name in argument_names.keys() {
+      if definition.signature.arg(name).is_none() {
+        return Err(format!("`{}` is not an argument of `{}`", name, original));
+      }
+    }
+
+    // alias argument name => original argument name
+    let mut renames: HashMap<String, String> = argument_names
+      .iter()
+      .map(|(original, alias)| (alias.clone(), original.clone()))
+      .collect();
+    renames.insert(alias_name.to_string(), original.to_string());
+    let target = Rc::downgrade(layer);
+    let original = original.to_string();
+    let callback: Callback = Rc::new(move |args: Apply<CorvusValue>, settings: &Settings| {
+      let layer = target.upgrade().ok_or(Error::Corvus("namespace was dropped".into()))?;
+      let definition = layer
+        .lookup(&original)
+        .ok_or_else(|| Error::Corvus(format!("`{}` is no longer defined", original)))?;
+      let mut renamed = Apply::with_capacity(args.iter().count());
+      for &(ref name, ref value) in args.iter() {
+        let name = renames.get(name).unwrap_or(name);
+        renamed.push_arg(name, value.clone());
+      }
+      (definition.callback)(renamed, settings)
+    });
+    layer.define(alias_definition(alias_name, &definition, argument_names, callback))
+  }
+
+  pub fn register_script(&self, script: &Rc<CompiledScript>) {
+    let mut scripts = self.scripts.borrow_mut();
+    scripts.retain(|script| script.upgrade().is_some());
+    scripts.push(Rc::downgrade(script));
+  }
+
+  fn is_local(&self, name: &str) -> bool {
+    self.local.borrow().iter().any(|definition| definition.name == name)
+  }
+
+  fn changed(&self) {
+    self.generation.set(self.generation.get() + 1);
+  }
+}
+
+/// The definition of `alias_name` as an alias of `original` that calls `callback`,
+/// see `NamespaceLayer::alias`.
+fn alias_definition(
+  alias_name: &str,
+  original: &Definition,
+  argument_names: &HashMap<String, String>,
+  callback: Callback,
+) -> Definition {
+  let mut signature = Signature::with_capacity(original.signature.args().count());
+  signature.set_return_type(original.signature.get_return_type().clone());
+  signature.set_total(original.signature.is_total());
+  for (i, arg) in original.signature.args().enumerate() {
+    let name = if i == 0 {
+      alias_name.to_string()
+    } else {
+      argument_names.get(&arg.name).cloned().unwrap_or_else(|| arg.name.clone())
+    };
+    signature.add_argument(Argument {
+      name: name,
+      ty: arg.ty.clone(),
+      required: arg.required,
+      variadic: arg.variadic,
+    });
+  }
+  let mut alias = Definition::new(signature, callback);
+  alias.capabilities = original.capabilities.clone();
+  alias.metadata = original.metadata.clone();
+  alias.metadata.argument_docs = original
+    .metadata
+    .argument_docs
+    .iter()
+    .map(|&(ref name, ref doc)| {
+      (argument_names.get(name).cloned().unwrap_or_else(|| name.clone()), doc.clone())
+    })
+    .collect();
+  alias.alias_of = Some(Rc::new(AliasOf {
+    original: original.name.clone(),
+    argument_names: argument_names.clone(),
+  }));
+  alias
+}
+
+/// Insert an entry for `definition` that calls whatever its name resolves to in
+/// `layer` at the time, with `layer`'s settings.
+fn insert_forwarder(
+  ns: &SharedNamespace<CorvusValue>,
+  layer: &Rc<NamespaceLayer>,
+  definition: &Definition,
+) -> Result<(), String> {
+  let layer = Rc::downgrade(layer);
+  let name = definition.name.clone();
+  ns.borrow_mut().insert(
+    definition.signature.clone(),
+    Box::new(move |args: Apply<CorvusValue>| {
+      let layer = layer.upgrade().ok_or(Error::Corvus("namespace was dropped".into()))?;
+      let callback = layer
+        .callback_of(&name)
+        .ok_or_else(|| Error::Corvus(format!("`{}` is no longer defined", name)))?;
+      callback(args, &layer.settings)
+    }),
+  )
+}
+
+/// Fail now if `definition` couldn't be inserted into a core namespace (e.g. it
+/// clashes with the prelude), rather than when the namespace is next built.
+fn check_insertable(definition: &Definition) -> Result<(), String> {
+  let name = definition.name.clone();
+  fresh_namespace()?.borrow_mut().insert(
+    definition.signature.clone(),
+    Box::new(move |_: Apply<CorvusValue>| Err(Error::Corvus(format!("`{}` was never inserted", name)))),
+  )
+}
+
+fn insert_into(
+  ns: &SharedNamespace<CorvusValue>,
+  definition: &Definition,
+  settings: &Rc<Settings>,
+) -> Result<(), String> {
+  let callback = definition.callback.clone();
+  let settings = settings.clone();
+  ns.borrow_mut().insert(
+    definition.signature.clone(),
+    Box::new(move |args: Apply<CorvusValue>| callback(args, &settings)),
+  )
+}
+
+/// What compiling against `definition` depends on, as text.
+fn definition_key(definition: &Definition) -> String {
+  let signature = &definition.signature;
+  let args: Vec<String> = signature
+    .args()
+    .map(|arg| {
+      let optional = if arg.required { "" } else { "?" };
+      let variadic = if arg.variadic { "..." } else { "" };
+      format!("{}{}{}: {}", arg.name, optional, variadic, type_syntax::format(&arg.ty))
+    })
+    .collect();
+  format!(
+    "{}({}) -> {}{} [{}]",
+    definition.name,
+    args.join(", "),
+    type_syntax::format(signature.get_return_type()),
+    if signature.is_total() { "" } else { "!" },
+    definition.capabilities.join(",")
+  )
+}
+
+/// Ways in which calls that were valid for `old` may be invalid for `new`.
+fn signature_problems(old: &Signature, new: &Signature) -> Vec<String> {
+  let mut problems = vec![];
+  for old_arg in old.args() {
+    match new.arg(&old_arg.name) {
+      None => problems.push(format!("argument `{}` was removed", old_arg.name)),
+      Some(new_arg) => {
+        if new_arg.ty != old_arg.ty {
+          problems.push(format!(
+            "argument `{}` changed type from {} to {}",
+            old_arg.name,
+            type_syntax::format(&old_arg.ty),
+            type_syntax::format(&new_arg.ty)
+          ));
+        }
+        if new_arg.required && !old_arg.required {
+          problems.push(format!("argument `{}` became required", old_arg.name));
+        }
+        if new_arg.variadic != old_arg.variadic {
+          problems.push(format!("argument `{}` changed whether it is variadic", old_arg.name));
+        }
+      }
+    }
+  }
+  for new_arg in new.args() {
+    if new_arg.required && old.arg(&new_arg.name).is_none() {
+      problems.push(format!("new argument `{}` is required", new_arg.name));
+    }
+  }
+  if old.get_return_type() != new.get_return_type() {
+    problems.push(format!(
+      "return type changed from {} to {}",
+      type_syntax::format(old.get_return_type()),
+      type_syntax::format(new.get_return_type())
+    ));
+  }
+  problems
+}
+
+fn fresh_namespace() -> Result<SharedNamespace<CorvusValue>, String> {
+  let ns: Namespace<CorvusValue> = Namespace::new_with_prelude()?;
+  Ok(ns.into_shared())
+}
@@ -0,0 +1,144 @@
+//! Groups of corvus_core prelude functions that a namespace can be restricted to
+
+/// Prelude modules and the functions (first keywords) they provide. corvus_core
+/// can't list the functions of its prelude, so this is kept by hand: the tests fail
+/// when a listed function leaves the prelude, but functions corvus_core adds are
+/// only callable (in `OTHER`) until they are listed here, and `Namespace#functions`
+/// and `#signatures` leave them out.
+pub const MODULES: &'static [(&'static str, &'static [&'static str])] = &[
+  ("math", &["calc"]),
+  ("lists", &["each", "countFrom"]),
+  ("strings", &["stringify"]),
+];
+
+/// The module of every prelude function missing from `MODULES`, so that
+/// selecting modules and capability groups covers the whole prelude even when
+/// corvus_core adds functions.
+pub const OTHER: &'static str = "other";
+
+/// The prelude module that provides `name`, assuming it's a prelude function.
+pub fn module_of(name: &str) -> &'static str {
+  MODULES
+    .iter()
+    .find(|&&(_, functions)| functions.contains(&name))
+    .map_or(OTHER, |&(module, _)| module)
+}
+
+fn module_names() -> Vec<&'static str> {
+  MODULES.iter().map(|&(module, _)| module).chain(Some(OTHER)).collect()
+}
+
+/// The prelude functions visible in a namespace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prelude {
+  All,
+  /// Only the functions of these modules, possibly none.
+  Only(Vec<&'static str>),
+}
+
+impl Prelude {
+  /// Select modules by name, failing on unknown ones.
+  pub fn select<I, S>(names: I) -> Result<Prelude, String>
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    let mut modules = vec![];
+    for name in names {
+      let name = name.as_ref();
+      match module_names().into_iter().find(|&module| module == name) {
+        Some(module) => if !modules.contains(&module) {
+          modules.push(module)
+        },
+        None => {
+          return Err(format!(
+            "unknown prelude module `{}`, expected one of {}",
+            name,
+            module_names().join(", ")
+          ));
+        }
+      }
+    }
+    Ok(Prelude::Only(modules))
+  }
+
+  pub fn modules(&self) -> Vec<&'static str> {
+    match *self {
+      Prelude::All => module_names(),
+      Prelude::Only(ref modules) => modules.clone(),
+    }
+  }
+
+  /// The selected functions listed in `MODULES`; those of `OTHER` aren't known
+  /// here.
+  pub fn function_names(&self) -> Vec<&'static str> {
+    let modules = self.modules();
+    MODULES
+      .iter()
+      .filter(|&&(module, _)| modules.contains(&module))
+      .flat_map(|&(_, functions)| functions.iter().cloned())
+      .collect()
+  }
+
+  /// Whether every prelude function may be called.
+  pub fn is_complete(&self) -> bool {
+    match *self {
+      Prelude::All => true,
+      Prelude::Only(_) => false,
+    }
+  }
+
+  /// Whether the prelude function `name` may be called.
+  pub fn allows(&self, name: &str) -> bool {
+    match *self {
+      Prelude::All => true,
+      Prelude::Only(ref modules) => modules.contains(&module_of(name)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use corvus_core::{INamespace, Namespace};
+
+  use value::CorvusValue;
+  use super::{module_of, Prelude, MODULES, OTHER};
+
+  #[test]
+  fn test_listed_functions_are_in_the_prelude() {
+    let ns: Namespace<CorvusValue> = Namespace::new_with_prelude().unwrap();
+    for &(module, functions) in MODULES {
+      for name in functions {
+        assert!(ns.get_signature(name).is_some(), "{} lists `{}`, which isn't in the prelude", module, name);
+      }
+    }
+  }
+
+  #[test]
+  fn test_select_modules() {
+    let prelude = Prelude::select(vec!["lists", "math", "lists"]).unwrap();
+    assert_eq!(prelude.modules(), vec!["lists", "math"]);
+    assert!(prelude.allows("each"));
+    assert!(!prelude.allows("stringify"));
+  }
+
+  #[test]
+  fn test_select_nothing() {
+    let prelude = Prelude::select(Vec::<String>::new()).unwrap();
+    assert!(!prelude.allows("calc"));
+  }
+
+  #[test]
+  fn test_unlisted_functions_are_in_other() {
+    assert_eq!(module_of("each"), "lists");
+    assert_eq!(module_of("notListed"), OTHER);
+    assert!(Prelude::All.modules().contains(&OTHER));
+    assert!(Prelude::select(vec![OTHER]).unwrap().allows("notListed"));
+    assert!(!Prelude::select(vec!["math"]).unwrap().allows("notListed"));
+  }
+
+  #[test]
+  fn test_select_unknown_module() {
+    assert!(Prelude::select(vec!["network"]).is_err());
+  }
+}
@@ -0,0 +1,90 @@
+//! `calc:` in interpreted scripts. The prelude's `calc` calculates with f64, so
+//! interpreted scripts call `NAME` instead, which applies Ruby's operators the way
+//! compiled scripts do: both modes then give the same results, and `Corvus::Money`
+//! stays exact wherever it comes from.
+
+use ruru::{AnyObject, Class, Object, RString};
+
+use corvus_core::{Apply, INamespace, SharedNamespace, Syntax};
+use corvus_core::signature::{Argument, Signature};
+
+use error::Error;
+use value::CorvusValue;
+
+/// Generated names start with ε, like the emitter's, to stay clear of user functions.
+pub const NAME: &'static str = "εcalc";
+
+/// Insert `NAME` into `ns`, with the signature of the prelude's `calc`.
+pub fn insert(ns: &SharedNamespace<CorvusValue>) -> Result<(), String> {
+  let signature = {
+    let ns = ns.borrow();
+    let calc = ns.get_signature("calc").ok_or("the prelude has no `calc`")?;
+    let mut signature = Signature::with_capacity(calc.args().count());
+    signature.set_return_type(calc.get_return_type().clone());
+    signature.set_total(calc.is_total());
+    for (i, arg) in calc.args().enumerate() {
+      signature.add_argument(Argument {
+        name: if i == 0 { NAME.to_string() } else { arg.name.clone() },
+        ty: arg.ty.clone(),
+        required: arg.required,
+        variadic: arg.variadic,
+      });
+    }
+    signature
+  };
+  ns.borrow_mut().insert(signature, Box::new(calc))
+}
+
+/// `stx` with its `calc:` applications calling `NAME` instead.
+pub fn route(stx: &Syntax) -> Syntax {
+  match *stx {
+    Syntax::Atom(_) | Syntax::Variable(_) => stx.clone(),
+    Syntax::Block(ref arg_names, ref body) => Syntax::Block(arg_names.clone(), route(body).into()),
+    Syntax::List(ref items) => Syntax::List(items.iter().map(route).collect()),
+    Syntax::Record(ref entries) => Syntax::Record(
+      entries
+        .iter()
+        .map(|&(ref name, ref value)| (name.clone(), route(value)))
+        .collect(),
+    ),
+    Syntax::Apply(ref apply) => {
+      let mut routed = Apply::with_capacity(apply.iter().count());
+      for (i, &(ref name, ref value)) in apply.iter().enumerate() {
+        let name = if i == 0 && name == "calc" { NAME.to_string() } else { name.clone() };
+        routed.push_arg(&name, route(value));
+      }
+      Syntax::Apply(routed.into())
+    }
+  }
+}
+
+/// calc: 1 plus: 2 times: 3, as `RubyEmitter::emit_math` writes it
+fn calc(args: Apply<CorvusValue>) -> Result<CorvusValue, Error> {
+  let mut args = args.iter();
+  let mut result = match args.next() {
+    Some(&(_, ref first)) => operand(first),
+    None => return Err(Error::Corvus("`calc:` needs a value".to_string())),
+  };
+  for &(ref op, ref value) in args {
+    let operator = match op.as_str() {
+      "plus" => "+",
+      "subtract" => "-",
+      "times" => "*",
+      "dividedBy" => "fdiv",
+      _ => return Err(Error::Corvus(format!("`{}:` is not a `calc:` operation", op))),
+    };
+    result = result.send(operator, Some(&[operand(value)]));
+  }
+  Ok(CorvusValue::from(result))
+}
+
+/// Money literals reach callbacks as text, see `CorvusValue::to_typed_ruby`.
+fn operand(value: &CorvusValue) -> AnyObject {
+  let obj = value.to_any_object();
+  match obj.try_convert_to::<RString>() {
+    Ok(text) => Class::from_existing("Corvus")
+      .get_nested_class("Money")
+      .send("parse", Some(&[text.to_any_object()])),
+    Err(_) => obj,
+  }
+}
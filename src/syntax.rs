@@ -0,0 +1,50 @@
+//! Static queries over parsed Corvus syntax
+
+use corvus_core::Syntax;
+
+/// The names of every function applied anywhere in `stx`, in order of appearance.
+pub fn function_names(stx: &Syntax) -> Vec<String> {
+  let mut names = vec![];
+  collect_function_names(stx, &mut names);
+  names
+}
+
+fn collect_function_names(stx: &Syntax, names: &mut Vec<String>) {
+  match *stx {
+    Syntax::Atom(_) | Syntax::Variable(_) => {}
+    Syntax::Block(_, ref body) => collect_function_names(body, names),
+    Syntax::List(ref items) => for item in items.iter() {
+      collect_function_names(item, names);
+    },
+    Syntax::Record(ref entries) => for &(_, ref value) in entries.iter() {
+      collect_function_names(value, names);
+    },
+    Syntax::Apply(ref apply) => {
+      let name = apply.func_name().to_string();
+      if !names.contains(&name) {
+        names.push(name);
+      }
+      for &(_, ref value) in apply.iter() {
+        collect_function_names(value, names);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::function_names;
+  use value::CorvusValue;
+  use corvus_core::{parse, Namespace, ParseRule};
+
+  #[test]
+  fn test_function_names() {
+    let ns: Namespace<CorvusValue> = Namespace::new_with_prelude().unwrap();
+    let stx = parse(
+      &ns,
+      ParseRule::term,
+      "each: { countFrom: 1 to: n } do: { i => [ s = stringify: i ] }",
+    ).unwrap();
+    assert_eq!(function_names(&stx), vec!["each", "countFrom", "stringify"]);
+  }
+}
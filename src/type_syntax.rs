@@ -0,0 +1,292 @@
+//! The textual syntax of types, as printed by `Type#to_s` and read by `Type.parse`:
+//!
+//! ```text
+//! Any  Number  String  Bool  Time  Money  primitives (`Num` and `Str` are accepted too)
+//! List<Number>                         lists
+//! [name: String, tags?: List<String>]  records, `?` marks optional fields
+//! [|name: String|]                     exact records, which allow no other fields
+//! (Number, Number) -> Bool             blocks
+//! a                                    unknown lowercase names are type variables
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use corvus_core::{RecordField, Type};
+
+/// Print `ty` in the syntax read by `parse`. Record fields are sorted by name.
+pub fn format(ty: &Type) -> String {
+  match *ty {
+    Type::Any => "Any".to_string(),
+    Type::Num => "Number".to_string(),
+    Type::Str => "String".to_string(),
+    Type::Bool => "Bool".to_string(),
+    Type::Time => "Time".to_string(),
+    Type::Money => "Money".to_string(),
+    Type::List(ref inner) => format!("List<{}>", format(inner)),
+    Type::Record(partial, ref fields) => {
+      let mut names: Vec<&String> = fields.keys().collect();
+      names.sort();
+      let fields: Vec<String> = names
+        .into_iter()
+        .map(|name| {
+          let field = &fields[name];
+          let marker = if field.is_optional() { "?" } else { "" };
+          format!("{}{}: {}", name, marker, format(field.get_type()))
+        })
+        .collect();
+      if partial {
+        format!("[{}]", fields.join(", "))
+      } else {
+        format!("[|{}|]", fields.join(", "))
+      }
+    }
+    Type::Block(ref inputs, ref output) => {
+      let inputs: Vec<String> = inputs.iter().map(format).collect();
+      format!("({}) -> {}", inputs.join(", "), format(output))
+    }
+    Type::Var(ref name) => name.clone(),
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+  pub message: String,
+  /// Character offset of the problem in the source
+  pub position: usize,
+  source: String,
+}
+
+impl fmt::Display for ParseError {
+  /// The message followed by the source with a caret under the problem.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{} at column {}\n  {}\n  {}^",
+      self.message,
+      self.position + 1,
+      self.source,
+      " ".repeat(self.position)
+    )
+  }
+}
+
+/// Parse a type expression. Names other than the primitives are looked up in
+/// `named`; lowercase names that aren't there are type variables.
+pub fn parse(src: &str, named: &HashMap<String, Type>) -> Result<Type, ParseError> {
+  let mut parser = Parser {
+    chars: src.chars().collect(),
+    pos: 0,
+    src: src,
+    named: named,
+  };
+  let ty = parser.parse_type()?;
+  parser.skip_whitespace();
+  if parser.pos < parser.chars.len() {
+    return Err(parser.error("unexpected input after the type"));
+  }
+  Ok(ty)
+}
+
+struct Parser<'a> {
+  chars: Vec<char>,
+  pos: usize,
+  src: &'a str,
+  named: &'a HashMap<String, Type>,
+}
+
+impl<'a> Parser<'a> {
+  fn parse_type(&mut self) -> Result<Type, ParseError> {
+    self.skip_whitespace();
+    match self.peek() {
+      Some('[') => self.parse_record(),
+      Some('(') => self.parse_block(),
+      Some(c) if c.is_alphabetic() => self.parse_named(),
+      Some(_) => Err(self.error("expected a type")),
+      None => Err(self.error("expected a type, found the end of the input")),
+    }
+  }
+
+  fn parse_named(&mut self) -> Result<Type, ParseError> {
+    let start = self.pos;
+    let name = self.identifier();
+    match name.as_str() {
+      "Any" => Ok(Type::Any),
+      "Number" | "Num" => Ok(Type::Num),
+      "String" | "Str" => Ok(Type::Str),
+      "Bool" | "Boolean" => Ok(Type::Bool),
+      "Time" => Ok(Type::Time),
+      "Money" => Ok(Type::Money),
+      "List" => {
+        self.expect('<')?;
+        let inner = self.parse_type()?;
+        self.expect('>')?;
+        Ok(Type::list_of(inner))
+      }
+      _ => match self.named.get(&name) {
+        Some(ty) => Ok(ty.clone()),
+        None if name.starts_with(char::is_lowercase) => Ok(Type::var(&name)),
+        None => Err(ParseError {
+          message: format!("unknown type `{}`", name),
+          position: start,
+          source: self.src.to_string(),
+        }),
+      },
+    }
+  }
+
+  fn parse_record(&mut self) -> Result<Type, ParseError> {
+    self.expect('[')?;
+    let exact = self.eat('|');
+    let close = if exact { '|' } else { ']' };
+    let mut fields: HashMap<String, RecordField> = HashMap::new();
+    self.skip_whitespace();
+    if self.peek() != Some(close) {
+      loop {
+        self.skip_whitespace();
+        let start = self.pos;
+        let name = self.identifier();
+        if name.is_empty() {
+          return Err(self.error("expected a field name"));
+        }
+        let optional = self.peek() == Some('?');
+        if optional {
+          self.pos += 1;
+        }
+        self.expect(':')?;
+        let ty = self.parse_type()?;
+        if fields.insert(name.clone(), RecordField::new(ty, optional)).is_some() {
+          return Err(ParseError {
+            message: format!("field `{}` is repeated", name),
+            position: start,
+            source: self.src.to_string(),
+          });
+        }
+        if !self.eat(',') {
+          break;
+        }
+      }
+    }
+    if exact {
+      self.expect('|')?;
+    }
+    self.expect(']')?;
+    Ok(Type::Record(!exact, fields))
+  }
+
+  fn parse_block(&mut self) -> Result<Type, ParseError> {
+    self.expect('(')?;
+    let mut inputs = vec![];
+    self.skip_whitespace();
+    if self.peek() != Some(')') {
+      loop {
+        inputs.push(self.parse_type()?);
+        if !self.eat(',') {
+          break;
+        }
+      }
+    }
+    self.expect(')')?;
+    self.expect('-')?;
+    self.expect('>')?;
+    let output = self.parse_type()?;
+    Ok(Type::Block(inputs, Box::new(output)))
+  }
+
+  fn identifier(&mut self) -> String {
+    let start = self.pos;
+    while self.peek().map_or(false, |c| c.is_alphanumeric() || c == '_') {
+      self.pos += 1;
+    }
+    self.chars[start..self.pos].iter().collect()
+  }
+
+  /// Skip whitespace, then consume `c` if it's next.
+  fn eat(&mut self, c: char) -> bool {
+    self.skip_whitespace();
+    if self.peek() == Some(c) {
+      self.pos += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), ParseError> {
+    if self.eat(c) {
+      Ok(())
+    } else {
+      Err(self.error(&format!("expected `{}`", c)))
+    }
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.peek().map_or(false, char::is_whitespace) {
+      self.pos += 1;
+    }
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).cloned()
+  }
+
+  fn error(&self, message: &str) -> ParseError {
+    let message = match self.peek() {
+      Some(c) => format!("{}, found `{}`", message, c),
+      None => message.to_string(),
+    };
+    ParseError {
+      message: message,
+      position: self.pos,
+      source: self.src.to_string(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_str(src: &str) -> Result<Type, ParseError> {
+    parse(src, &HashMap::new())
+  }
+
+  #[test]
+  fn round_trips_through_format() {
+    let src = "[f: (Number, Number) -> Bool, name: String, tags?: List<String>]";
+    let ty = parse_str(src).unwrap();
+    assert_eq!(format(&ty), src);
+    assert_eq!(parse_str(&format(&ty)).unwrap(), ty);
+  }
+
+  #[test]
+  fn round_trips_type_variables() {
+    let ty = parse_str("(List<a>, (a) -> b) -> List<b>").unwrap();
+    assert_eq!(format(&ty), "(List<a>, (a) -> b) -> List<b>");
+    assert_eq!(format(&Type::var("a")), "a");
+    assert_eq!(parse_str(&format(&ty)).unwrap(), ty);
+  }
+
+  #[test]
+  fn round_trips_exact_records() {
+    let ty = parse_str("[| id: Number |]").unwrap();
+    assert_eq!(format(&ty), "[|id: Number|]");
+    assert_eq!(parse_str("[||]").unwrap(), Type::Record(false, HashMap::new()));
+  }
+
+  #[test]
+  fn accepts_short_names_and_whitespace() {
+    assert_eq!(
+      parse_str(" ( Num ,Str )->List< Num > ").unwrap(),
+      Type::Block(vec![Type::Num, Type::Str], Box::new(Type::list_of(Type::Num)))
+    );
+  }
+
+  #[test]
+  fn reports_where_parsing_failed() {
+    let err = parse_str("[name String]").unwrap_err();
+    assert_eq!(err.position, 6);
+    assert_eq!(err.message, "expected `:`, found `S`");
+    assert_eq!(parse_str("Widget").unwrap_err().message, "unknown type `Widget`");
+  }
+}
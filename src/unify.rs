@@ -0,0 +1,299 @@
+//! Assignability and unification of types, so Ruby can check that a type fits
+//! where another is expected, e.g. that a script's return type fits the slot it is
+//! plugged into.
+//!
+//! `Any` accepts every type. Type variables are bound as they are met, and the
+//! bindings are returned as a substitution.
+
+use std::collections::HashMap;
+
+use corvus_core::{RecordField, Type};
+
+use type_syntax::format;
+
+/// Types bound to type variables, by variable name.
+pub type Substitution = HashMap<String, Type>;
+
+/// Where and why two types don't fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+  /// e.g. `people[].location`, empty at the top level
+  pub path: String,
+  pub expected: Type,
+  pub actual: Type,
+  pub message: String,
+}
+
+/// Whether values of `from` can be used where `to` is expected: records may have
+/// more fields than an open record requires, lists are covariant and blocks take
+/// inputs contravariantly.
+pub fn assignable(from: &Type, to: &Type) -> Result<Substitution, Mismatch> {
+  let mut checker = Checker::default();
+  checker.assign(from, to)?;
+  Ok(checker.subst)
+}
+
+/// The most specific type that values of both `a` and `b` have, with the type
+/// variables in either bound to make them agree.
+pub fn unify(a: &Type, b: &Type) -> Result<(Type, Substitution), Mismatch> {
+  let mut checker = Checker::default();
+  let ty = checker.unify(a, b)?;
+  Ok((substitute(&ty, &checker.subst), checker.subst))
+}
+
+/// Replace the type variables in `ty` that `subst` binds.
+pub fn substitute(ty: &Type, subst: &Substitution) -> Type {
+  if let Some(name) = var_name(ty) {
+    return subst
+      .get(&name)
+      .map(|bound| substitute(bound, subst))
+      .unwrap_or_else(|| ty.clone());
+  }
+  match *ty {
+    Type::List(ref inner) => Type::list_of(substitute(inner, subst)),
+    Type::Record(partial, ref fields) => Type::Record(
+      partial,
+      fields
+        .iter()
+        .map(|(name, field)| {
+          let field = RecordField::new(substitute(field.get_type(), subst), field.is_optional());
+          (name.clone(), field)
+        })
+        .collect(),
+    ),
+    Type::Block(ref inputs, ref output) => Type::Block(
+      inputs.iter().map(|input| substitute(input, subst)).collect(),
+      Box::new(substitute(output, subst)),
+    ),
+    _ => ty.clone(),
+  }
+}
+
+/// The name of a type variable, `None` for other types.
+pub fn var_name(ty: &Type) -> Option<String> {
+  match *ty {
+    Type::Var(ref name) => Some(name.clone()),
+    _ => None,
+  }
+}
+
+fn occurs(name: &str, ty: &Type) -> bool {
+  if var_name(ty).map_or(false, |var| var == name) {
+    return true;
+  }
+  match *ty {
+    Type::List(ref inner) => occurs(name, inner),
+    Type::Record(_, ref fields) => fields.values().any(|field| occurs(name, field.get_type())),
+    Type::Block(ref inputs, ref output) => {
+      inputs.iter().any(|input| occurs(name, input)) || occurs(name, output)
+    }
+    _ => false,
+  }
+}
+
+#[derive(Default)]
+struct Checker {
+  subst: Substitution,
+  path: Vec<String>,
+}
+
+impl Checker {
+  fn assign(&mut self, from: &Type, to: &Type) -> Result<(), Mismatch> {
+    if let Some(name) = var_name(to) {
+      return match self.subst.get(&name).cloned() {
+        Some(bound) => self.assign(from, &bound),
+        None => self.bind(name, to, from).map(|_| ()),
+      };
+    }
+    if let Some(name) = var_name(from) {
+      return match self.subst.get(&name).cloned() {
+        Some(bound) => self.assign(&bound, to),
+        None => self.bind(name, from, to).map(|_| ()),
+      };
+    }
+
+    match (from, to) {
+      (_, &Type::Any) => Ok(()),
+      (&Type::List(ref from_inner), &Type::List(ref to_inner)) => {
+        self.at("[]".to_string(), |c| c.assign(from_inner, to_inner))
+      }
+      (&Type::Record(from_open, ref from_fields), &Type::Record(to_open, ref to_fields)) => {
+        for name in sorted_names(to_fields.keys()) {
+          let to_field = &to_fields[name];
+          match from_fields.get(name) {
+            Some(from_field) => {
+              if from_field.is_optional() && !to_field.is_optional() {
+                return Err(self.mismatch(to, from, format!("field `{}` is optional", name)));
+              }
+              self.at(name.clone(), |c| c.assign(from_field.get_type(), to_field.get_type()))?;
+            }
+            None if to_field.is_optional() => {}
+            None => return Err(self.mismatch(to, from, format!("field `{}` is missing", name))),
+          }
+        }
+        if !to_open {
+          if from_open {
+            return Err(self.mismatch(to, from, "an open record may have other fields".to_string()));
+          }
+          if let Some(name) = sorted_names(from_fields.keys())
+            .into_iter()
+            .find(|name| !to_fields.contains_key(*name))
+          {
+            return Err(self.mismatch(to, from, format!("unexpected field `{}`", name)));
+          }
+        }
+        Ok(())
+      }
+      (&Type::Block(ref from_inputs, ref from_output), &Type::Block(ref to_inputs, ref to_output)) => {
+        if from_inputs.len() != to_inputs.len() {
+          return Err(self.mismatch(to, from, format!(
+            "takes {} inputs instead of {}",
+            from_inputs.len(),
+            to_inputs.len()
+          )));
+        }
+        for (i, (from_input, to_input)) in from_inputs.iter().zip(to_inputs).enumerate() {
+          self.at(format!("inputs[{}]", i), |c| c.assign(to_input, from_input))?;
+        }
+        self.at("output".to_string(), |c| c.assign(from_output, to_output))
+      }
+      _ if from == to => Ok(()),
+      _ => Err(self.mismatch(to, from, format!("expected {}, found {}", format(to), format(from)))),
+    }
+  }
+
+  fn unify(&mut self, a: &Type, b: &Type) -> Result<Type, Mismatch> {
+    if let Some(name) = var_name(a) {
+      return self.bind(name, a, b);
+    }
+    if let Some(name) = var_name(b) {
+      return self.bind(name, b, a);
+    }
+
+    match (a, b) {
+      (&Type::Any, _) => Ok(b.clone()),
+      (_, &Type::Any) => Ok(a.clone()),
+      (&Type::List(ref a_inner), &Type::List(ref b_inner)) => {
+        let inner = self.at("[]".to_string(), |c| c.unify(a_inner, b_inner))?;
+        Ok(Type::list_of(inner))
+      }
+      (&Type::Record(a_open, ref a_fields), &Type::Record(b_open, ref b_fields)) => {
+        let mut fields: HashMap<String, RecordField> = HashMap::new();
+        for name in sorted_names(a_fields.keys().chain(b_fields.keys())) {
+          let field = match (a_fields.get(name), b_fields.get(name)) {
+            (Some(a_field), Some(b_field)) => {
+              let ty = self.at(name.clone(), |c| c.unify(a_field.get_type(), b_field.get_type()))?;
+              RecordField::new(ty, a_field.is_optional() && b_field.is_optional())
+            }
+            (Some(field), None) | (None, Some(field)) => {
+              let other_open = if a_fields.contains_key(name) { b_open } else { a_open };
+              if !other_open && !field.is_optional() {
+                return Err(self.mismatch(a, b, format!("field `{}` is not in the exact record", name)));
+              }
+              field.clone()
+            }
+            (None, None) => unreachable!(),
+          };
+          fields.insert(name.clone(), field);
+        }
+        Ok(Type::Record(a_open && b_open, fields))
+      }
+      (&Type::Block(ref a_inputs, ref a_output), &Type::Block(ref b_inputs, ref b_output)) => {
+        if a_inputs.len() != b_inputs.len() {
+          return Err(self.mismatch(a, b, format!(
+            "blocks take {} and {} inputs",
+            a_inputs.len(),
+            b_inputs.len()
+          )));
+        }
+        let mut inputs = vec![];
+        for (i, (a_input, b_input)) in a_inputs.iter().zip(b_inputs).enumerate() {
+          inputs.push(self.at(format!("inputs[{}]", i), |c| c.unify(a_input, b_input))?);
+        }
+        let output = self.at("output".to_string(), |c| c.unify(a_output, b_output))?;
+        Ok(Type::Block(inputs, Box::new(output)))
+      }
+      _ if a == b => Ok(a.clone()),
+      _ => Err(self.mismatch(a, b, format!("{} and {} don't unify", format(a), format(b)))),
+    }
+  }
+
+  /// Bind the variable `var` called `name` to `other`, or unify its binding with it.
+  fn bind(&mut self, name: String, var: &Type, other: &Type) -> Result<Type, Mismatch> {
+    if var == other {
+      return Ok(var.clone());
+    }
+    if let Some(bound) = self.subst.get(&name).cloned() {
+      return self.unify(&bound, other);
+    }
+    if occurs(&name, other) {
+      return Err(self.mismatch(var, other, format!("{} occurs in {}", name, format(other))));
+    }
+    self.subst.insert(name, other.clone());
+    Ok(other.clone())
+  }
+
+  fn at<F, T>(&mut self, segment: String, f: F) -> Result<T, Mismatch>
+  where
+    F: FnOnce(&mut Self) -> Result<T, Mismatch>,
+  {
+    self.path.push(segment);
+    let result = f(self);
+    self.path.pop();
+    result
+  }
+
+  fn mismatch(&self, expected: &Type, actual: &Type, message: String) -> Mismatch {
+    let mut path = String::new();
+    for segment in &self.path {
+      if !path.is_empty() && !segment.starts_with('[') {
+        path.push('.');
+      }
+      path.push_str(segment);
+    }
+    Mismatch {
+      path: path,
+      expected: expected.clone(),
+      actual: actual.clone(),
+      message: message,
+    }
+  }
+}
+
+fn sorted_names<'a, I>(names: I) -> Vec<&'a String>
+where
+  I: Iterator<Item = &'a String>,
+{
+  let mut names: Vec<&String> = names.collect();
+  names.sort();
+  names.dedup();
+  names
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use type_syntax::parse;
+
+  fn ty(src: &str) -> Type {
+    parse(src, &HashMap::new()).unwrap()
+  }
+
+  #[test]
+  fn records_with_extra_fields_are_assignable_to_open_records() {
+    assert!(assignable(&ty("[id: Number, name: String]"), &ty("[id: Number]")).is_ok());
+    assert!(assignable(&ty("[id: Number, name: String]"), &ty("[|id: Number|]")).is_err());
+
+    let mismatch = assignable(&ty("List<[id: String]>"), &ty("List<[id: Number]>")).unwrap_err();
+    assert_eq!(mismatch.path, "[].id");
+    assert_eq!(mismatch.expected, Type::Num);
+  }
+
+  #[test]
+  fn unification_binds_type_variables() {
+    let (unified, subst) = unify(&ty("(a) -> List<a>"), &ty("(Number) -> b")).unwrap();
+    assert_eq!(unified, ty("(Number) -> List<Number>"));
+    assert_eq!(subst.get("a"), Some(&Type::Num));
+    assert!(unify(&ty("a"), &ty("List<a>")).is_err());
+  }
+}
@@ -2,10 +2,12 @@ use std::ffi::CStr;
 use std::iter::{FromIterator, Map};
 
 use error::Error;
-use corvus_core::{Block, List as IList, Record as IRecord, Value as IValue, WithError};
+use helpers::stringify_key;
+use corvus_core::{Block, List as IList, Record as IRecord, Type, Value as IValue, WithError};
 use ruby_sys::string; // for low level string reference
 use ruru;
-use ruru::{AnyObject, Array, Boolean, Fixnum, Float, Hash, NilClass, Object, Proc, RString, Symbol};
+use ruru::{AnyObject, Array, Boolean, Class, Fixnum, Float, Hash, NilClass, Object, Proc, RString,
+           Symbol};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CorvusValue(AnyObject, Option<Block<CorvusValue>>);
@@ -25,6 +27,75 @@ impl CorvusValue {
   pub fn to_any_object(&self) -> AnyObject {
     self.0.to_any_object()
   }
+
+  /// The currency and decimal amount of a `Corvus::Money`.
+  pub fn try_money(&self) -> Result<(String, String), Error> {
+    let obj = reject_nil(self.to_any_object())?;
+    let is_money = obj.send("is_a?", Some(&[money_class().to_any_object()]));
+    if !is_money.try_convert_to::<Boolean>().map(|b| b.to_bool()).unwrap_or(false) {
+      return Err(Error::Corvus("not an amount of money".to_string()));
+    }
+    let currency: RString = obj.send("currency", None).try_convert_to()?;
+    let amount: RString = obj
+      .send("amount", None)
+      .send("to_s", Some(&[RString::new("F").to_any_object()]))
+      .try_convert_to()?;
+    Ok((currency.to_string(), amount.to_string()))
+  }
+
+  /// This value as a Ruby `Time`, keeping sub-second precision. `Time`s, `DateTime`s,
+  /// `Date`s (at midnight, local time) and other objects with a `to_time`, such as
+  /// `ActiveSupport::TimeWithZone`, are converted with `to_time`. Integers are
+  /// seconds since the epoch, as times used to be passed.
+  pub fn try_ruby_time(&self) -> Result<AnyObject, Error> {
+    let obj = reject_nil(self.to_any_object())?;
+    if let Ok(secs) = obj.try_convert_to::<Fixnum>() {
+      return Ok(time_class().send("at", Some(&[secs.to_any_object()])));
+    }
+    // Strings only have `to_time` with ActiveSupport, and aren't times anyway
+    if obj.try_convert_to::<RString>().is_ok() || !obj.respond_to("to_time") {
+      return Err(Error::Corvus("not a time".to_string()));
+    }
+    Ok(obj.send("to_time", None))
+  }
+
+  /// Convert to the Ruby representation implied by `ty`: numbers become Floats, times
+  /// become `Time`s (see `try_ruby_time`), money strings become `Corvus::Money` and
+  /// records become Hashes with Symbol keys. Lists and records are converted
+  /// element-wise, anything else is returned unchanged.
+  pub fn to_typed_ruby(&self, ty: &Type) -> ruru::result::Result<AnyObject> {
+    let obj = self.to_any_object();
+    if obj.is_nil() {
+      return Ok(obj);
+    }
+    match *ty {
+      Type::Num => number_to_f64(obj).map(|number| Float::new(number).to_any_object()),
+      Type::Time => Ok(self.try_ruby_time().unwrap_or(obj)),
+      Type::Money => match obj.try_convert_to::<RString>() {
+        Ok(text) => Ok(money_class().send("parse", Some(&[text.to_any_object()]))),
+        Err(_) => Ok(obj),
+      },
+      Type::List(ref inner) => {
+        let items: Array = obj.try_convert_to()?;
+        let mut converted = Array::with_capacity(items.length());
+        for item in items {
+          converted.push(CorvusValue::from(item).to_typed_ruby(inner)?);
+        }
+        Ok(converted.to_any_object())
+      }
+      Type::Record(_, ref fields) => {
+        let record = Record(obj);
+        let mut hash = Hash::new();
+        for (name, field) in fields {
+          if let Some(value) = record.field(name) {
+            hash.store(Symbol::new(name), value.to_typed_ruby(field.get_type())?);
+          }
+        }
+        Ok(hash.to_any_object())
+      }
+      _ => Ok(obj),
+    }
+  }
 }
 
 impl From<AnyObject> for CorvusValue {
@@ -56,9 +127,11 @@ impl From<f64> for CorvusValue {
   }
 }
 
+/// Times made by the interpreter, which are seconds since the epoch
 impl From<u64> for CorvusValue {
-  fn from(i: u64) -> CorvusValue {
-    CorvusValue(Fixnum::new(i as i64).to_any_object(), None)
+  fn from(secs: u64) -> CorvusValue {
+    let secs = Fixnum::new(secs as i64).to_any_object();
+    CorvusValue(time_class().send("at", Some(&[secs])), None)
   }
 }
 
@@ -110,12 +183,21 @@ impl IValue for CorvusValue {
   type List = List;
   type Record = Record;
 
+  /// Floats, and Integers converted to Floats.
   fn try_number(&self) -> Result<f64, Error> {
-    self.non_nil(|v| v.try_convert_to().map(|f: Float| f.to_f64()))
+    self.non_nil(number_to_f64)
   }
 
+  /// Whole seconds since the epoch, as the interpreter calculates with them. The
+  /// `Value` trait fixes this as `u64`, so fractions of a second are dropped and
+  /// earlier times can't be represented; see "Times" in the README.
   fn try_time(&self) -> Result<u64, Error> {
-    self.non_nil(|v| v.try_convert_to().map(|i: Fixnum| i.to_i64() as u64))
+    let secs: Fixnum = self.try_ruby_time()?.send("to_i", None).try_convert_to()?;
+    let secs = secs.to_i64();
+    if secs < 0 {
+      return Err(Error::Corvus("times before 1970 can't be used in calculations".to_string()));
+    }
+    Ok(secs as u64)
   }
 
   fn try_bool(&self) -> Result<bool, Error> {
@@ -197,6 +279,18 @@ impl Record {
   fn at_any_object<T: Object>(&self, key: T) -> Option<CorvusValue> {
     nil_to_none(self.0.send("[]", Some(&[key.to_any_object()]))).map(CorvusValue::from)
   }
+
+  /// The field `name`, whether its key is a Symbol or a String.
+  pub fn field(&self, name: &str) -> Option<CorvusValue> {
+    self
+      .at(name)
+      .or_else(|| self.at_any_object(RString::new(name)))
+  }
+
+  /// The Symbol and String keys, as Strings.
+  pub fn key_names(&self) -> Vec<String> {
+    self.keys().into_iter().filter_map(|key| stringify_key(key).ok()).collect()
+  }
 }
 
 impl IntoIterator for Record {
@@ -256,6 +350,26 @@ impl IRecord<CorvusValue> for Record {
   }
 }
 
+fn number_to_f64(obj: AnyObject) -> ruru::result::Result<f64> {
+  if let Ok(int) = obj.try_convert_to::<Fixnum>() {
+    return Ok(int.to_i64() as f64);
+  }
+  // Bignums are Integers too, but not Fixnums
+  let integer = obj.send("is_a?", Some(&[Class::from_existing("Integer").to_any_object()]));
+  if integer.try_convert_to::<Boolean>().map(|b| b.to_bool()).unwrap_or(false) {
+    return obj.send("to_f", None).try_convert_to().map(|f: Float| f.to_f64());
+  }
+  obj.try_convert_to().map(|f: Float| f.to_f64())
+}
+
+fn time_class() -> Class {
+  Class::from_existing("Time")
+}
+
+fn money_class() -> Class {
+  Class::from_existing("Corvus").get_nested_class("Money")
+}
+
 fn nil_to_none(o: AnyObject) -> Option<AnyObject> {
   if o.is_nil() {
     None
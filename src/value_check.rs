@@ -0,0 +1,163 @@
+//! Checking Ruby values against types, reporting where in the value each problem is.
+
+use std::fmt;
+
+use ruru::{Object, RString};
+
+use corvus_core::{Type, Value as IValue};
+
+use type_syntax::format;
+use value::CorvusValue;
+
+/// How much of a value's `inspect` to show in errors
+const EXCERPT_LENGTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueError {
+  /// e.g. `people[3].location.lat`, empty for the value itself
+  pub path: String,
+  pub expected: Type,
+  /// The Ruby class of the value found, `NilClass` for missing fields
+  pub actual: String,
+  pub excerpt: String,
+  pub message: String,
+}
+
+impl fmt::Display for ValueError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.path.is_empty() {
+      write!(f, "{}", self.message)
+    } else {
+      write!(f, "{}: {}", self.path, self.message)
+    }
+  }
+}
+
+/// The ways `value` is not a `ty`. Unless `all` is set, checking stops at the
+/// first error.
+pub fn check(ty: &Type, value: &CorvusValue, all: bool) -> Vec<ValueError> {
+  let mut checker = Checker {
+    all: all,
+    errors: vec![],
+  };
+  checker.check(ty, Some(value), "");
+  checker.errors
+}
+
+struct Checker {
+  all: bool,
+  errors: Vec<ValueError>,
+}
+
+impl Checker {
+  fn check(&mut self, ty: &Type, value: Option<&CorvusValue>, path: &str) {
+    if !self.all && !self.errors.is_empty() {
+      return;
+    }
+    let value = match value {
+      Some(value) => value,
+      None => return self.push(path, ty, None, "missing required field".to_string()),
+    };
+    let matches = match *ty {
+      Type::Any => true,
+      Type::Num => value.try_number().is_ok(),
+      Type::Str => value.try_string().is_ok(),
+      Type::Bool => value.try_bool().is_ok(),
+      Type::Time => value.try_ruby_time().is_ok(),
+      Type::Money => value.try_money().is_ok(),
+      Type::List(ref inner) => match value.try_list() {
+        Ok(list) => {
+          for (i, item) in list.into_iter().enumerate() {
+            self.check(inner, Some(&item), &format!("{}[{}]", path, i));
+          }
+          true
+        }
+        Err(_) => false,
+      },
+      Type::Record(partial, ref fields) => match value.try_record() {
+        Ok(record) => {
+          let mut names: Vec<&String> = fields.keys().collect();
+          names.sort();
+          for name in names {
+            let field = &fields[name];
+            match record.field(name) {
+              None if field.is_optional() => {}
+              found => self.check(field.get_type(), found.as_ref(), &field_path(path, name)),
+            }
+          }
+          if !partial {
+            for name in record.key_names() {
+              if !fields.contains_key(&name) {
+                let found = record.field(&name);
+                let message = format!("unexpected field `{}`", name);
+                self.push(&field_path(path, &name), ty, found.as_ref(), message);
+              }
+            }
+          }
+          true
+        }
+        Err(_) => false,
+      },
+      Type::Block(_, _) => value.callable(),
+      // type variables
+      _ => true,
+    };
+    if !matches {
+      let message = format!("expected {}, found {}", format(ty), class_name(Some(value)));
+      self.push(path, ty, Some(value), message);
+    }
+  }
+
+  fn push(&mut self, path: &str, expected: &Type, value: Option<&CorvusValue>, message: String) {
+    if !self.all && !self.errors.is_empty() {
+      return;
+    }
+    self.errors.push(ValueError {
+      path: path.to_string(),
+      expected: expected.clone(),
+      actual: class_name(value),
+      excerpt: excerpt(value),
+      message: message,
+    });
+  }
+}
+
+fn field_path(path: &str, name: &str) -> String {
+  if path.is_empty() {
+    name.to_string()
+  } else {
+    format!("{}.{}", path, name)
+  }
+}
+
+fn class_name(value: Option<&CorvusValue>) -> String {
+  match value {
+    None => "NilClass".to_string(),
+    Some(value) if value.callable() => "Proc".to_string(),
+    Some(value) => value
+      .to_any_object()
+      .class()
+      .send("name", None)
+      .try_convert_to::<RString>()
+      .map(|name| name.to_string())
+      .unwrap_or_default(),
+  }
+}
+
+fn excerpt(value: Option<&CorvusValue>) -> String {
+  let inspected = match value {
+    None => return "nil".to_string(),
+    Some(value) => value
+      .to_any_object()
+      .send("inspect", None)
+      .try_convert_to::<RString>()
+      .map(|s| s.to_string())
+      .unwrap_or_default(),
+  };
+  if inspected.chars().count() <= EXCERPT_LENGTH {
+    inspected
+  } else {
+    let truncated: String = inspected.chars().take(EXCERPT_LENGTH).collect();
+    format!("{}...", truncated)
+  }
+}
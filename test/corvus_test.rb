@@ -42,4 +42,680 @@ class CorvusTest < Minitest::Test
     script = @compiler.compile 'calc: x plus: y'
     script.input_types
   end
+
+  def test_optional_arguments_fall_back_to_their_default
+    @compiler.define do |f|
+      f.arg 'greet', :string
+      f.arg 'punctuation', :string, default: '!'
+      f.returns :string
+      f.callback { |args| args['greet'] + args['punctuation'] }
+    end
+
+    assert_equal 'hi!', @compiler.compile('greet: "hi"').call
+    assert_equal 'hi?', @compiler.compile('greet: "hi" punctuation: "?"').call
+  end
+
+  def test_defaults_must_match_the_argument_type
+    assert_raises(ArgumentError) do
+      @compiler.define do |f|
+        f.arg 'pad', :string
+        f.arg 'width', :number, default: 'wide'
+        f.returns :string
+        f.callback { |args| args['pad'] }
+      end
+    end
+  end
+
+  def test_args_can_be_read_as_a_hash
+    @compiler.define do |f|
+      f.arg 'sum', :number, variadic: true
+      f.arg 'label', :string, optional: true
+      f.returns :string
+      f.callback do |args|
+        assert_equal 'sum', args.function_name
+        refute args.key?(:label)
+        assert_equal 'total', args.fetch(:label, 'total')
+        "#{args.fetch(:label, 'total')}: #{args.to_h[:sum].sum}"
+      end
+    end
+
+    assert_equal 'total: 3.0', @compiler.compile('sum: 1 sum: 2').call
+  end
+
+  def test_variadic_args_without_values_are_not_keys
+    @compiler.define do |f|
+      f.arg 'tally', :number
+      f.arg 'plus', :number, variadic: true
+      f.returns :bool
+      f.callback { |args| args.key?(:plus) }
+    end
+
+    refute @compiler.compile('tally: 1').call
+    assert @compiler.compile('tally: 1 plus: 2').call
+  end
+
+  def test_typed_args_convert_records_to_symbol_keys
+    @compiler.types.define 'Point', x: :number, y: :number
+    @compiler.define do |f|
+      f.arg 'xOf', 'Point'
+      f.returns :number
+      f.callback { |args| args.typed(:xOf)[:x] }
+    end
+
+    assert_equal 3.0, @compiler.compile('xOf: p').call(p: { 'x' => 3, 'y' => 4 })
+    assert_equal (2**70).to_f, @compiler.compile('xOf: p').call(p: { 'x' => 2**70, 'y' => 4 })
+  end
+
+  def test_callbacks_can_take_keyword_arguments
+    @compiler.define do |f|
+      f.arg 'repeat', :string
+      f.arg 'times', :number, default: 2.0
+      f.returns :string
+      f.callback ->(repeat:, times:) { repeat * times.to_i }
+    end
+
+    assert_equal 'abab', @compiler.compile('repeat: "ab"').call
+    assert_equal 'ababab', @compiler.compile('repeat: "ab" times: 3').call_interpreted({})
+  end
+
+  def test_callbacks_and_defaults_survive_garbage_collection
+    @compiler.define do |f|
+      f.arg 'exclaim', :string
+      f.arg 'mark', :string, default: '!' * 3
+      f.returns :string
+      f.callback ->(exclaim:, mark:) { exclaim + mark }
+    end
+    tenant = Corvus::Compiler.new.tap { |parent| define_greeting(parent, 'hello') }.fork
+    GC.start
+
+    assert_equal 'hey!!!', @compiler.compile('exclaim: "hey"').call
+    assert_equal 'hello you', tenant.compile('greet: "you"').call
+  end
+
+  def test_keyword_callbacks_must_match_the_signature
+    assert_raises(ArgumentError) do
+      @compiler.define do |f|
+        f.arg 'repeat', :string
+        f.arg 'times', :number
+        f.returns :string
+        f.callback ->(repeat:) { repeat }
+      end
+    end
+  end
+
+  class Greeter
+    extend Corvus::Exposed
+
+    corvus_function :greet, { greet: :string, punctuation: :string, names: :string },
+                    returns: :string
+    def greet(*names, greet:, punctuation: '!')
+      "#{greet} #{names.join(' & ')}#{punctuation}"
+    end
+  end
+
+  def test_it_can_expose_ruby_methods
+    @compiler.expose_all(Greeter.new)
+
+    assert_equal 'hi a & b!', @compiler.compile('greet: "hi" names: "a" names: "b"').call
+    assert_equal 'hi a?', @compiler.compile('greet: "hi" names: "a" punctuation: "?"').call
+  end
+
+  def test_namespaces_resolve_exposed_types
+    ns = Corvus::Namespace.new
+    ns.expose_all(Greeter.new)
+
+    assert_equal Corvus::Type::String, ns.signature('greet').return_type
+    assert_equal 'yo a!', Corvus::Compiler.new(ns).compile('greet: "yo" names: "a"').call
+  end
+
+  def test_it_can_validate_callback_results
+    @compiler.validate_results = true
+    @compiler.define do |f|
+      f.arg 'double', :number
+      f.returns :number
+      f.callback { |args| (args['double'] * 2).to_s }
+    end
+
+    error = assert_raises(TypeError) { @compiler.compile('double: 2').call }
+    assert_match(/`double` \(defined at .*corvus_test\.rb:\d+\)/, error.message)
+  end
+
+  class Miscounter
+    extend Corvus::Exposed
+
+    corvus_function :tally, { tally: :string, items: :string }, returns: :number
+    def tally(*items, tally:)
+      items.join(tally)
+    end
+  end
+
+  def test_errors_point_at_exposed_methods_with_splats
+    @compiler.validate_results = true
+    @compiler.expose_all(Miscounter.new)
+
+    error = assert_raises(TypeError) { @compiler.compile('tally: "," items: "a"').call }
+    line = Miscounter.instance_method(:tally).source_location.last
+    assert_match(/defined at .*corvus_test\.rb:#{line}\)/, error.message)
+  end
+
+  def test_integer_results_are_valid_numbers
+    @compiler.validate_results = true
+    @compiler.define do |f|
+      f.arg 'count', :string
+      f.returns :number
+      f.callback { |args| args['count'].length }
+    end
+
+    assert_equal 3, @compiler.compile('count: "abc"').call
+  end
+
+  def test_around_requires_a_hook
+    ns = Corvus::Namespace.new
+    assert_raises(ArgumentError) { ns.around }
+    assert_raises(ArgumentError) { ns.around(->(call) { call.proceed }) { |call| call.proceed } }
+  end
+
+  def test_around_hooks_wrap_defined_functions
+    calls = []
+    @compiler.define do |f|
+      f.arg 'shout', :string
+      f.returns :string
+      f.callback ->(shout:) { shout.upcase }
+    end
+    @compiler.around do |call|
+      calls << call.function_name
+      call.args['shout'] == 'stub' ? 'stubbed' : call.proceed + '!'
+    end
+
+    script = @compiler.compile('shout: s')
+    assert_equal 'HI!', script.call(s: 'hi')
+    assert_equal 'stubbed', script.call_interpreted(s: 'stub')
+    assert_equal %w[shout shout], calls
+  end
+
+  def test_callbacks_can_see_the_execution_context
+    user = Struct.new(:name).new('ada')
+    @compiler.define do |f|
+      f.arg 'greeting', :string
+      f.returns :string
+      f.callback { |args| "#{args['greeting']}, #{args.context.name}" }
+    end
+
+    script = @compiler.compile('greeting: g')
+    assert_equal 'hi, ada', script.call({ g: 'hi' }, user)
+    assert_equal 'yo, ada', script.call_interpreted({ 'g' => 'yo' }, user)
+  end
+
+  def test_contexts_are_dropped_when_callbacks_raise
+    @compiler.define do |f|
+      f.arg 'whoami', :string
+      f.returns :string
+      f.callback { |args| args['whoami'] == 'fail' ? raise('boom') : args.context.to_s }
+    end
+    script = @compiler.compile('whoami: w')
+
+    assert_raises(RuntimeError) { script.call_interpreted({ w: 'fail' }, 'request 1') }
+    assert_raises(RuntimeError) { script.call({ w: 'fail' }, 'request 2') }
+    assert_equal '', @compiler.corvus_call(:whoami, 'me')
+  end
+
+  def test_keyword_callbacks_can_take_the_context
+    user = Struct.new(:name).new('ada')
+    @compiler.define do |f|
+      f.arg 'salute', :string
+      f.returns :string
+      f.callback ->(salute:, context:) { "#{salute}, #{context.name}" }
+    end
+
+    script = @compiler.compile('salute: g')
+    assert_equal 'hi, ada', script.call({ g: 'hi' }, user)
+    assert_equal 'yo, ada', script.call_interpreted({ g: 'yo' }, user)
+    @compiler.around { |call| call.proceed.upcase }
+    assert_equal 'HEY, ADA', script.call({ g: 'hey' }, user)
+  end
+
+  def define_greeting(compiler, greeting)
+    compiler.define do |f|
+      f.arg 'greet', :string
+      f.returns :string
+      f.callback ->(greet:) { "#{greeting} #{greet}" }
+    end
+  end
+
+  def test_forks_inherit_and_override_definitions
+    define_greeting(@compiler, 'hello')
+    tenant = @compiler.fork
+    script = 'greet: "you"'
+
+    assert_equal 'hello you', tenant.compile(script).call
+
+    define_greeting(tenant, 'howdy')
+    assert_equal 'howdy you', tenant.compile(script).call
+    assert_equal 'hello you', @compiler.compile(script).call
+  end
+
+  def test_forks_see_later_parent_definitions
+    tenant = @compiler.fork
+    define_greeting(@compiler, 'hello')
+
+    assert_equal 'hello you', tenant.compile('greet: "you"').call
+  end
+
+  def test_forks_follow_later_parent_hooks_and_options
+    define_greeting(@compiler, 'hello')
+    tenant = @compiler.fork
+    calls = []
+    @compiler.around { |call| calls << :parent; call.proceed }
+    tenant.around { |call| calls << :tenant; call.proceed }
+
+    assert_equal 'hello you', tenant.compile('greet: "you"').call
+    assert_equal %i[parent tenant], calls
+
+
+    ns = Corvus::Namespace.new
+    fork = ns.fork
+    ns.validate_results = !ns.validate_results?
+    assert_equal ns.validate_results?, fork.validate_results?
+  end
+
+  def test_namespaces_can_be_restricted_to_prelude_modules
+    compiler = Corvus::Compiler.new(Corvus::Namespace.new(prelude: [:math]))
+
+    assert_equal 4.0, compiler.compile('calc: 1 plus: 3').call
+    assert_raises(TypeError) { compiler.compile('stringify: 1') }
+    assert_equal [:math], Corvus::Namespace.new(prelude: [:math]).prelude_modules
+    assert_equal %i[math lists strings other], Corvus::Namespace.new.prelude_modules
+    refute_equal @compiler.cache_key('x'), compiler.cache_key('x')
+  end
+
+  def test_namespaces_can_start_empty
+    ns = Corvus::Namespace.new(prelude: false)
+    assert_empty ns.prelude_modules
+    assert_empty ns.functions
+  end
+
+  def define_range(compiler)
+    compiler.define do |f|
+      f.arg 'rangeFrom', :number
+      f.arg 'through', :number
+      f.returns compiler.types.list_of(:number)
+      f.callback ->(rangeFrom:, through:) { (rangeFrom.to_i..through.to_i).map(&:to_f) }
+    end
+  end
+
+  def test_functions_can_be_aliased_with_renamed_arguments
+    define_range(@compiler)
+    @compiler.alias 'numbersFrom', 'rangeFrom', 'through' => 'upTo'
+
+    assert_equal [1.0, 2.0], @compiler.compile('numbersFrom: 1 upTo: 2').call
+  end
+
+  def test_aliases_follow_redefinitions
+    define_range(@compiler)
+    @compiler.alias 'numbersFrom', 'rangeFrom', 'through' => 'upTo'
+    script = @compiler.compile('numbersFrom: 1 upTo: 2')
+
+    error = assert_raises(Corvus::RedefinitionError) do
+      @compiler.redefine do |f|
+        f.arg 'rangeFrom', :string
+        f.arg 'through', :number
+        f.returns :string
+        f.callback ->(rangeFrom:, through:) { rangeFrom * through.to_i }
+      end
+    end
+    assert(error.problems.any? { |p| p.include?('numbersFrom: 1 upTo: 2') })
+    assert_equal [1.0, 2.0], script.call
+
+    @compiler.redefine(force: true) do |f|
+      f.arg 'rangeFrom', :string
+      f.arg 'through', :number
+      f.returns :string
+      f.callback ->(rangeFrom:, through:) { rangeFrom * through.to_i }
+    end
+    assert_raises(TypeError) { @compiler.compile('numbersFrom: 1 upTo: 2') }
+    assert_equal 'abab', @compiler.compile('numbersFrom: "ab" upTo: 2').call
+  end
+
+  def test_aliased_functions_cannot_be_undefined
+    define_range(@compiler)
+    @compiler.alias 'numbersFrom', 'rangeFrom', 'through' => 'upTo'
+
+    assert_raises(TypeError) { @compiler.undefine 'rangeFrom' }
+    @compiler.undefine 'numbersFrom'
+    @compiler.undefine 'rangeFrom'
+    assert_raises(TypeError) { @compiler.compile('numbersFrom: 1 upTo: 2') }
+  end
+
+  def test_functions_can_be_undefined
+    define_range(@compiler)
+    @compiler.undefine 'rangeFrom'
+
+    assert_raises(TypeError) { @compiler.compile('rangeFrom: 1 through: 2') }
+  end
+
+  def test_redefine_reports_the_scripts_it_would_break
+    define_range(@compiler)
+    script = @compiler.compile('rangeFrom: 1 through: 2')
+
+    error = assert_raises(Corvus::RedefinitionError) do
+      @compiler.redefine do |f|
+        f.arg 'rangeFrom', :string
+        f.arg 'through', :number
+        f.returns :string
+        f.callback ->(rangeFrom:, through:) { rangeFrom }
+      end
+    end
+    assert_includes error.problems, 'argument `rangeFrom` changed type from Number to String'
+    assert(error.problems.any? { |p| p.include?('rangeFrom: 1 through: 2') })
+    assert_equal [1.0, 2.0], script.call
+  end
+
+  def test_redefining_changes_the_cache_key
+    define_range(@compiler)
+    key = @compiler.cache_key('rangeFrom: 1 through: 2')
+    @compiler.redefine(force: true) do |f|
+      f.arg 'rangeFrom', :number
+      f.arg 'through', :number
+      f.returns :string
+      f.callback ->(rangeFrom:, through:) { "#{rangeFrom}..#{through}" }
+    end
+
+    refute_equal key, @compiler.cache_key('rangeFrom: 1 through: 2')
+  end
+
+  def test_scripts_can_be_restricted_to_capabilities
+    @compiler.define do |f|
+      f.arg 'lookupUser', :string
+      f.returns :string
+      f.capabilities :db
+      f.callback ->(lookupUser:) { lookupUser.upcase }
+    end
+
+    assert_equal 'ADA', @compiler.compile('lookupUser: "ada"', allow: [:db]).call
+    assert_raises(TypeError) { @compiler.compile('lookupUser: "ada"', allow: [:math]) }
+    assert_raises(TypeError) { @compiler.compile('lookupUser: "ada"', deny: [:db]) }
+    assert_equal 4.0, @compiler.compile('calc: 1 plus: 3', allow: [:math]).call
+  end
+
+  def test_capabilities_are_enforced_at_runtime
+    @compiler.define do |f|
+      f.arg 'lookupUser', :string
+      f.returns :string
+      f.capabilities :db
+      f.callback ->(lookupUser:) { lookupUser.upcase }
+    end
+    script = @compiler.compile('stringify: 1', allow: [:strings])
+
+    assert_raises(TypeError) { script.corvus_call(:lookupUser, 'ada') }
+  end
+
+  def test_functions_carry_metadata
+    @compiler.define do |f|
+      f.arg 'shout', :string, doc: 'the text to shout'
+      f.returns :string
+      f.description 'Upper-cases its argument'
+      f.example 'shout: "hi"'
+      f.category :strings
+      f.callback ->(shout:) { shout.upcase }
+    end
+
+    metadata = @compiler.metadata('shout')
+    assert_equal 'Upper-cases its argument', metadata[:description]
+    assert_equal({ 'shout' => 'the text to shout' }, metadata[:arguments])
+    assert_equal ['shout: "hi"'], metadata[:examples]
+    assert_equal 'strings', metadata[:category]
+    assert_nil metadata[:deprecated]
+    assert_equal metadata, @compiler.signature('shout').metadata
+  end
+
+  def test_scripts_calling_deprecated_functions_have_warnings
+    @compiler.define do |f|
+      f.arg 'yell', :string
+      f.returns :string
+      f.deprecated replacement: 'shout'
+      f.callback ->(yell:) { yell.upcase }
+    end
+
+    script = @compiler.compile('yell: "hi"')
+    assert_equal 'HI', script.call
+    assert_equal ['`yell` is deprecated, use `shout` instead'], script.warnings
+    assert_equal [], @compiler.compile('stringify: 1').warnings
+  end
+
+  def test_function_reference
+    @compiler.define do |f|
+      f.arg 'shout', :string, doc: 'the text to shout'
+      f.arg 'times', :number, default: 1
+      f.returns :string
+      f.total!
+      f.description 'Upper-cases <text>'
+      f.callback ->(shout:, times:) { shout.upcase * times.to_i }
+    end
+    assert_equal 'HI', @compiler.compile('shout: "hi"').call
+    reference = @compiler.reference
+
+    string = Corvus::Type::String
+    markdown = reference.to_markdown
+    assert_includes markdown, "shout: #{string} [times: #{Corvus::Type::Number}] -> #{string}"
+    assert_includes markdown, "- `shout` (`#{string}`): the text to shout"
+    assert_includes reference.to_html, 'Upper-cases &lt;text&gt;'
+
+    functions = JSON.parse(reference.to_json)['functions']
+    assert_includes functions.map { |f| f['name'] }, 'calc'
+    shout = functions.find { |f| f['name'] == 'shout' }
+    assert_equal true, shout['total']
+    assert_equal string.to_s, shout['returns']
+  end
+
+  def test_types_round_trip_through_json_schema
+    type = @compiler.types.resolve(name: :string,
+                                   born: { type: :time, optional: true },
+                                   scores: @compiler.types.list_of(:number))
+    schema = type.to_json_schema
+
+    assert_equal 'object', schema['type']
+    assert_equal %w[name scores], schema['required']
+    assert_equal 'date-time', schema['properties']['born']['format']
+    assert_equal({ 'type' => 'array', 'items' => { 'type' => 'number' } },
+                 schema['properties']['scores'])
+    assert_equal type, Corvus::Type.from_json_schema(JSON.generate(schema))
+  end
+
+  def test_blocks_and_type_variables_round_trip_through_json_schema
+    type = Corvus::Type.parse('[items: List<a>, key: (a) -> String]')
+    schema = type.to_json_schema
+
+    assert_equal 'a Corvus block: (a) -> String', schema['properties']['key']['description']
+    assert_equal({ 'x-corvus-type' => 'a' }, schema['properties']['items']['items'])
+    assert_equal type, Corvus::Type.from_json_schema(JSON.generate(schema))
+  end
+
+  def test_unsupported_json_schema_is_rejected
+    assert_raises(ArgumentError) { Corvus::Type.from_json_schema('type' => 'null') }
+  end
+
+  def test_namespace_signatures_export_as_json_schema
+    define_range(@compiler)
+    schema = @compiler.json_schema['definitions']['rangeFrom']
+
+    assert_equal %w[rangeFrom through], schema['required']
+    assert_equal false, schema['additionalProperties']
+    assert_equal 'array', schema['x-corvus-returns']['type']
+  end
+
+  def test_type_expressions_round_trip_through_to_s
+    type = Corvus::Type.parse('[name: String, tags?: List<String>, f: (Num, Num) -> Bool]')
+
+    assert_equal type, Corvus::Type.parse(type.to_s)
+    assert_equal Corvus::Type.list(Corvus::Type::Number), @compiler.types.resolve('List<Number>')
+    error = assert_raises(ArgumentError) { Corvus::Type.parse('[name String]') }
+    assert_includes error.message, 'column 7'
+  end
+
+  def test_exact_records_reject_extra_fields
+    exact = @compiler.types.exact_record(id: :number)
+    open = @compiler.types.resolve(id: :number)
+
+    refute exact.open?
+    assert open.open?
+    assert_equal [], exact.check_value(id: 1)
+    assert_equal ['name: unexpected field `name`'],
+                 exact.check_value(id: 1, 'name' => 'x').map(&:to_s)
+    assert_equal [], open.check_value(id: 1, name: 'x')
+    assert_equal exact, Corvus::Type.parse(exact.to_s)
+  end
+
+  def test_types_can_be_inspected
+    type = Corvus::Type.parse('[name: String, tags?: List<String>, f: (Number) -> Bool]')
+
+    assert_equal :record, type.kind
+    assert_equal %w[tags], type.optional_fields
+    assert_equal %w[f name], type.required_fields
+    assert_equal Corvus::Type::String, type.fields['tags'].element_type
+    assert_equal [Corvus::Type::Number], type.fields['f'].inputs
+    assert_equal :bool, type.fields['f'].output.kind
+    assert_nil Corvus::Type::Number.element_type
+    assert_equal({ kind: :list, element: { kind: :string } }, type.to_h[:fields]['tags'][:type])
+    assert_includes type.each_type.map { |_, path| path }, ['f', :output]
+  end
+
+  def test_assignability_and_unification
+    person = Corvus::Type.parse('[name: String, age: Number]')
+
+    assert person.assignable_to?(Corvus::Type.parse('[name: String]'))
+    refute person.assignable_to?(Corvus::Type.parse('[|name: String|]'))
+
+    assignment = Corvus::Type.parse('List<[name: Number]>').assignment_to(Corvus::Type.parse('List<[name: String]>'))
+    refute assignment.ok?
+    assert_equal '[].name', assignment.mismatch.path
+    assert_equal Corvus::Type::String, assignment.mismatch.expected
+
+    unification = Corvus::Type.parse('List<a>').unify(Corvus::Type.list(Corvus::Type::Number))
+    assert unification.ok?
+    assert_equal Corvus::Type.list(Corvus::Type::Number), unification.type
+  end
+
+  def test_check_value_reports_where_values_are_wrong
+    type = Corvus::Type.parse('[people: List<[name: String, location: [lat: Number]]>]')
+    value = { people: [{ name: 'Ada', location: { lat: 51.5 } },
+                       { name: 'Bob', location: { lat: 'north' } },
+                       { location: { lat: 1 } }] }
+    assert_equal [], Corvus::Type::Number.check_value(2**70)
+
+    errors = type.check_value(value)
+    assert_equal ['people[1].location.lat', 'people[2].name'], errors.map(&:path)
+    assert_equal Corvus::Type::Number, errors.first.expected
+    assert_equal 'String', errors.first.actual
+    assert_equal '"north"', errors.first.excerpt
+    assert_equal 1, type.check_value(value, all: false).size
+  end
+
+  def test_coerce_converts_form_input
+    type = Corvus::Type.parse('[firstName: String, age: Number, active: Bool, joined: Time, scores: List<Number>]')
+    coercion = type.coerce('First Name' => 'Ada', 'age' => '36', 'active' => 'true',
+                           'joined' => '2017-05-01T00:00:00Z', 'scores' => ['1.5', 2])
+
+    assert coercion.ok?
+    assert_equal({ firstName: 'Ada', age: 36.0, active: true,
+                   joined: Time.utc(2017, 5, 1), scores: [1.5, 2.0] }, coercion.value)
+
+    failed = type.coerce('firstName' => 'Ada', 'age' => 'old', 'active' => 'no',
+                         'joined' => 0, 'scores' => [])
+    assert_equal %w[active age], failed.errors.map(&:path)
+  end
+
+  def test_types_can_be_inferred_from_samples
+    type = Corvus::Type.infer({ 'name' => 'Ada', 'tags' => [], 'id' => 1 },
+                              samples: [{ 'name' => 'Bob', 'tags' => ['x'], 'id' => 'b2', 'email' => nil }])
+
+    assert_equal Corvus::Type.parse('[email?: Any, id: Any, name: String, tags: List<String>]'), type
+    assert_equal Corvus::Type.list(Corvus::Type::Number), Corvus::Type.infer([1, 2.5])
+  end
+
+  def test_values_satisfy_the_type_inferred_from_them
+    values = [1, 2**70, 2.5, Rational(1, 3), :sym, 'text', Date.new(2017, 5, 1), [1, nil],
+              { id: 1, 'tags' => [:a, 'b'], at: nil }, { 1 => 'one' }, [{ a: 1 }, { b: 'x' }]]
+
+    values.each do |value|
+      assert_equal [], Corvus::Type.infer(value).check_value(value), value.inspect
+    end
+    assert_equal Corvus::Type::Any, Corvus::Type.infer(:sym)
+  end
+
+  def test_samples_satisfy_their_type
+    type = Corvus::Type.parse('[name: String, tags?: List<String>, scores: List<Number>, at: Time, ok: Bool]')
+
+    20.times do |seed|
+      assert_equal [], type.check_value(type.sample(seed: seed))
+    end
+    assert_equal type.sample(seed: 7), type.sample(seed: 7)
+  end
+
+  def test_sampled_times_are_whole_seconds_after_the_epoch
+    50.times do |seed|
+      time = Corvus::Type::Time.sample(seed: seed)
+      assert_operator time.to_i, :>=, 0
+      assert_equal 0, time.subsec
+    end
+  end
+
+  def test_counterexamples_are_shrunk
+    numbers = Corvus::Type.list(Corvus::Type::Number)
+
+    assert_equal [0.0, 0.0, 0.0], numbers.counterexample(seed: 1) { |xs| xs.size < 3 }
+    assert_nil numbers.counterexample(seed: 1) { |xs| xs.is_a?(Array) }
+  end
+
+  def test_money_is_exact_and_refuses_mixed_currencies
+    price = Corvus::Money.parse('0.10 USD')
+
+    assert_equal Corvus::Money.parse('USD', '0.30'), price + price + price
+    assert_equal Corvus::Money.parse('0.25 USD'), 2.5 * price
+    assert_equal 0.5, Corvus::Money.parse('0.05 USD') / price
+    error = assert_raises(Corvus::CurrencyMismatch) { price + Corvus::Money.parse('1 EUR') }
+    assert_match(/currencies differ/, error.message)
+  end
+
+  def test_money_values_have_a_type
+    type = Corvus::Type.parse('[total: Money]')
+
+    assert_equal [], type.check_value(total: Corvus::Money.parse('9.99 EUR'))
+    assert_equal ['total'], type.check_value(total: 9.99).map(&:path)
+    assert_equal Corvus::Money.parse('9.99 EUR'), type.coerce('total' => '9.99 EUR').value[:total]
+    assert_equal Corvus::Type::Money, Corvus::Type.infer(Corvus::Money.parse('1 GBP'))
+    assert_equal type, Corvus::Type.from_json_schema(type.to_json_schema)
+  end
+
+  def test_calc_on_money_is_decimal_in_both_modes
+    script = @compiler.compile('calc: price plus: price plus: price')
+    price = Corvus::Money.parse('0.10 USD')
+
+    assert_equal Corvus::Money.parse('0.30 USD'), script.call(price: price)
+    assert_equal Corvus::Money.parse('0.30 USD'), script.call_interpreted(price: price)
+    assert_equal 0.30000000000000004, script.call_interpreted(price: 0.1)
+    mixed = @compiler.compile('calc: a plus: b')
+    assert_raises(Corvus::CurrencyMismatch) { mixed.call(a: price, b: Corvus::Money.parse('1 EUR')) }
+    assert_raises(Corvus::CurrencyMismatch) { mixed.call_interpreted(a: price, b: Corvus::Money.parse('1 EUR')) }
+  end
+
+  def test_calc_on_money_literals_is_decimal_in_both_modes
+    script = @compiler.compile('calc: 0.10 USD plus: 0.20 USD times: 3')
+
+    assert_equal Corvus::Money.parse('0.90 USD'), script.call
+    assert_equal Corvus::Money.parse('0.90 USD'), script.call_interpreted
+  end
+
+  def test_times_come_back_as_ruby_times
+    type = Corvus::Type.parse('[at: Time]')
+    moon_landing = Time.utc(1969, 7, 20, 20, 17, 40.5)
+
+    assert_equal [], type.check_value(at: moon_landing)
+    assert_equal [], type.check_value(at: Date.new(2017, 5, 1))
+    assert_equal ['at'], type.check_value(at: '2017-05-01').map(&:path)
+    assert_equal moon_landing, type.coerce(at: '1969-07-20T20:17:40.5Z').value[:at]
+
+    script = @compiler.compile('t')
+    assert_equal moon_landing, script.call(t: moon_landing)
+    assert_equal moon_landing, script.call_interpreted(t: moon_landing)
+  end
 end
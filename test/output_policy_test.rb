@@ -0,0 +1,98 @@
+require "test_helper"
+
+# Script#call and Script#call_interpreted must give identical output
+class OutputPolicyTest < Minitest::Test
+  SCRIPTS = {
+    '[ a=1 b="two" c=[ 1 2.5 ] ]' => {},
+    '[ outer=[ inner=[ x=calc: 1 plus: 2 ] ] ]' => {},
+    '[ 1 2 3 ]' => {},
+    'calc: 1 plus: 3' => {},
+    '"text"' => {},
+    '[ when=t on=d ]' => { t: Time.utc(1969, 7, 20, 20, 17, 40.5), d: Date.new(2017, 5, 1) },
+    '[ given=r ]' => { r: { 'k' => 1.0, v: [{ 'deep' => 2.0 }] } },
+    '[ q=calc: a dividedBy: b s=calc: a plus: b n=a ]' => { a: 7, b: 2 },
+  }.freeze
+
+  POLICIES = [
+    {},
+    { keys: :string },
+    { keys: :indifferent },
+    { integers: true },
+    { time_class: DateTime },
+    { keys: :string, integers: true, freeze: true },
+  ].freeze
+
+  def test_both_modes_give_identical_output
+    POLICIES.each do |policy|
+      compiler = Corvus::Compiler.new
+      compiler.output_policy = policy
+      SCRIPTS.each do |src, globals|
+        script = compiler.compile(src)
+        compiled = script.call(**globals)
+        interpreted = script.call_interpreted(globals)
+
+        message = "#{src} with #{policy}"
+        assert_equal compiled, interpreted, message
+        assert_equal structure(compiled), structure(interpreted), message
+      end
+    end
+  end
+
+  def test_defaults_use_symbol_keys_floats_and_times
+    script = Corvus::Compiler.new.compile('[ n=1 t=t ]')
+    time = Time.utc(2017, 5, 1, 12, 0, 0.25)
+
+    [script.call(t: Date.new(2017, 5, 1)), script.call_interpreted(t: Date.new(2017, 5, 1))].each do |result|
+      assert_equal %i[n t], result.keys.sort
+      assert_kind_of Float, result[:n]
+      assert_equal Date.new(2017, 5, 1).to_time, result[:t]
+    end
+    assert_equal time, script.call_interpreted(t: time)[:t]
+  end
+
+  def test_integer_globals_give_floats_in_both_modes
+    script = Corvus::Compiler.new.compile('[ q=calc: a dividedBy: b s=calc: a plus: b ]')
+
+    [script.call(a: 7, b: 2), script.call_interpreted(a: 7, b: 2)].each do |result|
+      assert_equal 3.5, result[:q]
+      assert_equal 9.0, result[:s]
+      assert_kind_of Float, result[:s]
+    end
+  end
+
+  def test_policies_convert_keys_numbers_and_times
+    compiler = Corvus::Compiler.new
+    compiler.output_policy = { keys: :indifferent, integers: true, time_class: DateTime, freeze: true }
+    result = compiler.compile('[ n=1 x=1.5 t=t ]').call_interpreted(t: Time.utc(2017, 5, 1))
+
+    assert_equal 1, result[:n]
+    assert_equal 1, result['n']
+    assert_equal 1.5, result['x']
+    assert_kind_of DateTime, result[:t]
+    assert result.frozen?
+  end
+
+  def test_unknown_key_styles_are_rejected
+    assert_raises(ArgumentError) { Corvus::OutputPolicy.new(keys: :camel) }
+  end
+
+  def test_cache_keys_depend_on_the_policy
+    compiler = Corvus::Compiler.new
+    key = compiler.cache_key('x')
+    compiler.output_policy = { keys: :string }
+    refute_equal key, compiler.cache_key('x')
+  end
+
+  private
+
+  # Classes and key types all the way down, which assert_equal doesn't compare
+  def structure(value)
+    case value
+    when Hash
+      entries = value.map { |key, item| [key.to_s, key.class, structure(item)] }
+      [value.class, value.frozen?, entries.sort_by(&:first)]
+    when Array then [Array, value.frozen?, value.map { |item| structure(item) }]
+    else [value.class, value.frozen?]
+    end
+  end
+end